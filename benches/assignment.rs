@@ -0,0 +1,42 @@
+mod common;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use trackers_rs::{compute_iou_batch, AssignmentSolver};
+
+use common::crowd_scene;
+
+/// Row-major `-IoU` cost matrix of a crowd scene of `n` tracks and detections.
+fn crowd_cost(n: usize) -> Vec<f32> {
+    let (tracks, detections) = crowd_scene(n, n as u64);
+    let iou = compute_iou_batch(&tracks, &detections);
+    let mut cost = Vec::with_capacity(n * n);
+    for i in 0..n {
+        for j in 0..n {
+            cost.push(-iou[(i, j)]);
+        }
+    }
+    cost
+}
+
+fn bench_solvers(c: &mut Criterion) {
+    let mut group = c.benchmark_group("assignment");
+    for n in [10, 50, 100, 250, 500, 1000] {
+        if n >= 500 {
+            group.sample_size(10);
+        }
+        let cost = crowd_cost(n);
+        for (name, solver) in [
+            ("exact", AssignmentSolver::Exact),
+            ("greedy", AssignmentSolver::Greedy),
+            ("sparse", AssignmentSolver::Sparse),
+        ] {
+            group.bench_with_input(BenchmarkId::new(name, n), &cost, |b, cost| {
+                b.iter(|| solver.solve(cost, n, n, -0.1).unwrap())
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_solvers);
+criterion_main!(benches);
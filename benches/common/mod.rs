@@ -0,0 +1,44 @@
+/// Linear congruential generator, enough for reproducible synthetic scenes.
+pub struct Lcg(pub u64);
+
+impl Lcg {
+    pub fn next_f32(&mut self) -> f32 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+/// Tracks and detections of a crowd scene with `n` people of about 40x100
+/// px at constant density, each detected slightly off its predicted box.
+/// Detections are shuffled so the diagonal is not the answer.
+pub fn crowd_scene(n: usize, seed: u64) -> (Vec<[f32; 4]>, Vec<[f32; 4]>) {
+    let mut rng = Lcg(seed);
+    let side = (n as f32).sqrt() * 60.0;
+
+    let tracks: Vec<[f32; 4]> = (0..n)
+        .map(|_| {
+            let x = rng.next_f32() * side;
+            let y = rng.next_f32() * side;
+            let w = 30.0 + rng.next_f32() * 20.0;
+            let h = 80.0 + rng.next_f32() * 40.0;
+            [x, y, x + w, y + h]
+        })
+        .collect();
+    let mut detections: Vec<[f32; 4]> = tracks
+        .iter()
+        .map(|b| {
+            let dx = (rng.next_f32() - 0.5) * 10.0;
+            let dy = (rng.next_f32() - 0.5) * 10.0;
+            [b[0] + dx, b[1] + dy, b[2] + dx, b[3] + dy]
+        })
+        .collect();
+    for i in (1..n).rev() {
+        let j = (rng.next_f32() * (i + 1) as f32) as usize;
+        detections.swap(i, j.min(i));
+    }
+
+    (tracks, detections)
+}
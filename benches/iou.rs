@@ -0,0 +1,47 @@
+mod common;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use nalgebra::DMatrix;
+use trackers_rs::{compute_iou, compute_iou_batch, Float};
+
+use common::crowd_scene;
+
+/// Pair-by-pair IoU matrix, the scalar path the batched kernel must match.
+fn scalar_iou_batch<F: Float>(predicted: &[[F; 4]], detections: &[[F; 4]]) -> DMatrix<F> {
+    DMatrix::from_fn(predicted.len(), detections.len(), |i, j| {
+        compute_iou(&predicted[i], &detections[j])
+    })
+}
+
+fn bench_precision<F: Float>(c: &mut Criterion, precision: &str) {
+    let mut group = c.benchmark_group(format!("iou_batch_{precision}"));
+    for n in [16, 100, 250, 500, 1000] {
+        if n >= 500 {
+            group.sample_size(20);
+        }
+        let (tracks, detections) = crowd_scene(n, n as u64);
+        let tracks: Vec<[F; 4]> = tracks.iter().map(|b| b.map(F::of_f32)).collect();
+        let detections: Vec<[F; 4]> = detections.iter().map(|b| b.map(F::of_f32)).collect();
+        assert_eq!(
+            compute_iou_batch(&tracks, &detections),
+            scalar_iou_batch(&tracks, &detections),
+            "batched IoU differs from the scalar path"
+        );
+
+        group.bench_with_input(BenchmarkId::new("scalar", n), &n, |b, _| {
+            b.iter(|| scalar_iou_batch(&tracks, &detections))
+        });
+        group.bench_with_input(BenchmarkId::new("batched", n), &n, |b, _| {
+            b.iter(|| compute_iou_batch(&tracks, &detections))
+        });
+    }
+    group.finish();
+}
+
+fn bench_iou_batch(c: &mut Criterion) {
+    bench_precision::<f32>(c, "f32");
+    bench_precision::<f64>(c, "f64");
+}
+
+criterion_group!(benches, bench_iou_batch);
+criterion_main!(benches);
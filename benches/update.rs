@@ -0,0 +1,76 @@
+mod common;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use trackers_rs::{ByteTrackConfig, ByteTrackTracker, Detection, Tracker};
+
+use common::{crowd_scene, Lcg};
+
+/// `frames` frames of a crowd of `n` people walking at constant velocity,
+/// with jittered boxes, a mix of high and low scores and a few misses.
+fn crowd_sequence(n: usize, frames: usize) -> Vec<Vec<Detection>> {
+    let (start, _) = crowd_scene(n, n as u64);
+    let mut rng = Lcg(frames as u64);
+    let velocities: Vec<[f32; 2]> = (0..n)
+        .map(|_| [(rng.next_f32() - 0.5) * 6.0, (rng.next_f32() - 0.5) * 2.0])
+        .collect();
+
+    (0..frames)
+        .map(|t| {
+            start
+                .iter()
+                .zip(&velocities)
+                .filter_map(|(b, v)| {
+                    if rng.next_f32() < 0.05 {
+                        return None;
+                    }
+                    let dx = v[0] * t as f32 + (rng.next_f32() - 0.5) * 2.0;
+                    let dy = v[1] * t as f32 + (rng.next_f32() - 0.5) * 2.0;
+                    let score = if rng.next_f32() > 0.2 { 0.9 } else { 0.3 };
+                    Some(Detection::new(
+                        [b[0] + dx, b[1] + dy, b[2] + dx, b[3] + dy],
+                        0,
+                        score,
+                    ))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn bench_update(c: &mut Criterion) {
+    let mut group = c.benchmark_group("update");
+    for n in [10, 100, 500] {
+        if n >= 500 {
+            group.sample_size(10);
+        }
+        let sequence = crowd_sequence(n, 50);
+
+        let mut bytetrack = ByteTrackTracker::new(ByteTrackConfig::default()).unwrap();
+
+        group.bench_with_input(BenchmarkId::new("update", n), &sequence, |b, sequence| {
+            b.iter(|| {
+                bytetrack.reset();
+                for detections in sequence {
+                    bytetrack.update(detections).unwrap();
+                }
+            })
+        });
+        group.bench_with_input(
+            BenchmarkId::new("update_into", n),
+            &sequence,
+            |b, sequence| {
+                let mut output = Vec::new();
+                b.iter(|| {
+                    bytetrack.reset();
+                    for detections in sequence {
+                        bytetrack.update_into(detections, &mut output).unwrap();
+                    }
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_update);
+criterion_main!(benches);
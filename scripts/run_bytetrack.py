@@ -39,6 +39,8 @@ for frame_idx, frame_detections in enumerate(detections_list):
             {
                 "box": tracked.xyxy[i].tolist(),
                 "tracker_id": int(tracked.tracker_id[i]),
+                "class_id": int(tracked.class_id[i]),
+                "score": float(tracked.confidence[i]),
             }
         )
 
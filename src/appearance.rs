@@ -0,0 +1,333 @@
+use std::collections::VecDeque;
+
+use nalgebra::DMatrix;
+use serde::{Deserialize, Serialize};
+
+use crate::config::check_unit_range;
+use crate::detection::Detection;
+use crate::error::{ConfigError, Result};
+use crate::float::Float;
+use crate::iou::compute_iou;
+use crate::kalman::KalmanBoxTracker;
+
+/// How a track accumulates the embeddings of its matched detections.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum GalleryMode {
+    /// Single feature smoothed as `momentum * old + (1 - momentum) * new`.
+    Ema { momentum: f64 },
+    /// Up to `budget` most recent features, distance is the minimum over them.
+    History { budget: usize },
+}
+
+/// Appearance-based association for the first (high-confidence) stage of
+/// [`ByteTrackTracker`](crate::ByteTrackTracker).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppearanceConfig {
+    pub gallery: GalleryMode,
+    /// Pairs with a larger cosine distance are never matched.
+    pub max_cosine_distance: f64,
+    /// Weight of the appearance distance in the fused cost, the rest goes to
+    /// IoU distance.
+    pub appearance_weight: f64,
+    /// Minimum IoU for pairs matched by appearance. `0.0` lets a track be
+    /// re-identified anywhere in the frame after a long occlusion.
+    pub minimum_proximity_iou: f64,
+}
+
+impl Default for AppearanceConfig {
+    fn default() -> Self {
+        Self {
+            gallery: GalleryMode::Ema { momentum: 0.9 },
+            max_cosine_distance: 0.25,
+            appearance_weight: 0.5,
+            minimum_proximity_iou: 0.0,
+        }
+    }
+}
+
+impl AppearanceConfig {
+    pub fn with_gallery(mut self, gallery: GalleryMode) -> Self {
+        self.gallery = gallery;
+        self
+    }
+
+    pub fn with_max_cosine_distance(mut self, max_cosine_distance: f64) -> Self {
+        self.max_cosine_distance = max_cosine_distance;
+        self
+    }
+
+    pub fn with_appearance_weight(mut self, appearance_weight: f64) -> Self {
+        self.appearance_weight = appearance_weight;
+        self
+    }
+
+    pub fn with_minimum_proximity_iou(mut self, minimum_proximity_iou: f64) -> Self {
+        self.minimum_proximity_iou = minimum_proximity_iou;
+        self
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        match self.gallery {
+            GalleryMode::Ema { momentum } => check_unit_range("gallery.momentum", momentum)?,
+            GalleryMode::History { budget } => {
+                if budget == 0 {
+                    return Err(ConfigError::EmptyGalleryBudget.into());
+                }
+            }
+        }
+        if !(0.0..=2.0).contains(&self.max_cosine_distance) {
+            return Err(ConfigError::ThresholdOutOfRange {
+                name: "max_cosine_distance",
+                value: self.max_cosine_distance,
+            }
+            .into());
+        }
+        check_unit_range("appearance_weight", self.appearance_weight)?;
+        check_unit_range("minimum_proximity_iou", self.minimum_proximity_iou)?;
+
+        Ok(())
+    }
+}
+
+/// Re-identification features collected by a track.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct FeatureGallery<F: Float = f64> {
+    features: VecDeque<Vec<F>>,
+}
+
+impl<F: Float> Default for FeatureGallery<F> {
+    fn default() -> Self {
+        Self {
+            features: VecDeque::new(),
+        }
+    }
+}
+
+impl<F: Float> FeatureGallery<F> {
+    pub fn is_empty(&self) -> bool {
+        self.features.is_empty()
+    }
+
+    pub fn add(&mut self, embedding: &[F], mode: GalleryMode) {
+        let Some(feature) = normalized(embedding) else {
+            return;
+        };
+
+        match mode {
+            GalleryMode::Ema { momentum } => match self.features.front_mut() {
+                Some(smoothed) if smoothed.len() == feature.len() => {
+                    let momentum = F::of_f64(momentum);
+                    for (s, &f) in smoothed.iter_mut().zip(&feature) {
+                        *s = momentum * *s + (F::one() - momentum) * f;
+                    }
+                    if let Some(renormalized) = normalized(smoothed) {
+                        *smoothed = renormalized;
+                    }
+                }
+                _ => {
+                    self.features.clear();
+                    self.features.push_back(feature);
+                }
+            },
+            GalleryMode::History { budget } => {
+                while self.features.len() >= budget {
+                    self.features.pop_front();
+                }
+                self.features.push_back(feature);
+            }
+        }
+    }
+
+    /// Smallest cosine distance between `embedding` and the stored features,
+    /// `None` when the gallery has no feature of the same dimension.
+    pub fn distance(&self, embedding: &[F]) -> Option<F> {
+        self.features
+            .iter()
+            .filter_map(|feature| cosine_distance(feature, embedding))
+            .reduce(|a, b| a.min(b))
+    }
+}
+
+/// Cosine distance `1 - cos(a, b)` in `0.0..=2.0`, `None` for vectors of
+/// different length or zero norm.
+pub fn cosine_distance<F: Float>(a: &[F], b: &[F]) -> Option<F> {
+    if a.len() != b.len() {
+        return None;
+    }
+
+    let mut dot = F::zero();
+    let mut norm_a = F::zero();
+    let mut norm_b = F::zero();
+    for (&x, &y) in a.iter().zip(b) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+
+    let norm = (norm_a * norm_b).sqrt();
+    if norm <= F::zero() {
+        return None;
+    }
+
+    Some((F::one() - dot / norm).clamp(F::zero(), F::of_f64(2.0)))
+}
+
+fn normalized<F: Float>(embedding: &[F]) -> Option<Vec<F>> {
+    let norm = embedding.iter().map(|&x| x * x).sum::<F>().sqrt();
+    if norm <= F::zero() || !norm.is_finite() {
+        return None;
+    }
+    Some(embedding.iter().map(|&x| x / norm).collect())
+}
+
+/// Builds the similarity matrix of the appearance stage.
+///
+/// Pairs where both sides carry features get `1 - cost` with
+/// `cost = w * cosine_distance + (1 - w) * (1 - iou)`, gated by
+/// `max_cosine_distance` and `minimum_proximity_iou`. Other pairs fall back
+/// to plain IoU gated by `minimum_iou_threshold`, and class-aware mode gates
+/// pairs of different classes. Gated pairs get `-inf`, which association
+/// never matches, like the similarity of an
+/// [`AssociationCost`](crate::AssociationCost).
+pub fn fused_similarity<F: Float>(
+    config: &AppearanceConfig,
+    tracks: &[KalmanBoxTracker<F>],
+    predicted_boxes: &[[F; 4]],
+    detections: &[Detection<F>],
+    minimum_iou_threshold: f64,
+    class_aware: bool,
+) -> DMatrix<F> {
+    let mut similarity = DMatrix::zeros(tracks.len(), detections.len());
+    let gated = F::of_f64(f64::NEG_INFINITY);
+    let max_cosine_distance = F::of_f64(config.max_cosine_distance);
+    let minimum_proximity_iou = F::of_f64(config.minimum_proximity_iou);
+    let minimum_iou_threshold = F::of_f64(minimum_iou_threshold);
+    let w = F::of_f64(config.appearance_weight);
+
+    for (i, track) in tracks.iter().enumerate() {
+        for (j, detection) in detections.iter().enumerate() {
+            if class_aware && track.class_id != detection.class_id {
+                similarity[(i, j)] = gated;
+                continue;
+            }
+
+            let iou = compute_iou(&predicted_boxes[i], &detection.box_coords);
+            let appearance = detection
+                .embedding
+                .as_deref()
+                .and_then(|embedding| track.gallery().distance(embedding));
+
+            similarity[(i, j)] = match appearance {
+                Some(distance)
+                    if distance <= max_cosine_distance && iou >= minimum_proximity_iou =>
+                {
+                    let one = F::one();
+                    (one - (w * distance + (one - w) * (one - iou))).max(F::zero())
+                }
+                Some(_) => gated,
+                None if iou >= minimum_iou_threshold => iou,
+                None => gated,
+            };
+        }
+    }
+
+    similarity
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gallery(embeddings: &[&[f64]], mode: GalleryMode) -> FeatureGallery<f64> {
+        let mut gallery = FeatureGallery::default();
+        for embedding in embeddings {
+            gallery.add(embedding, mode);
+        }
+        gallery
+    }
+
+    #[test]
+    fn ema_gallery_smooths_and_renormalizes() {
+        let mode = GalleryMode::Ema { momentum: 0.9 };
+        let smoothed = gallery(&[&[2.0, 0.0], &[0.0, 3.0]], mode);
+        let norm = 0.82f64.sqrt();
+        assert_eq!(smoothed.features.len(), 1);
+        assert!((smoothed.features[0][0] - 0.9 / norm).abs() < 1e-15);
+        assert!((smoothed.features[0][1] - 0.1 / norm).abs() < 1e-15);
+
+        // A zero embedding is ignored, one of another dimension restarts it.
+        let restarted = gallery(&[&[1.0, 0.0], &[0.0, 0.0], &[0.0, 0.0, 5.0]], mode);
+        assert_eq!(restarted.features, [vec![0.0, 0.0, 1.0]]);
+    }
+
+    #[test]
+    fn history_gallery_keeps_the_latest_features() {
+        let mode = GalleryMode::History { budget: 2 };
+        let history = gallery(&[&[1.0, 0.0], &[0.0, 2.0], &[-3.0, 0.0]], mode);
+        assert_eq!(history.features, [vec![0.0, 1.0], vec![-1.0, 0.0]]);
+
+        // The distance is the smallest over the kept features.
+        assert_eq!(history.distance(&[0.0, 1.0]), Some(0.0));
+        assert_eq!(history.distance(&[1.0, 0.0]), Some(1.0));
+        assert_eq!(history.distance(&[1.0, 0.0, 0.0]), None);
+        assert_eq!(FeatureGallery::<f64>::default().distance(&[1.0]), None);
+    }
+
+    #[test]
+    fn cosine_distance_ranges_from_zero_to_two() {
+        assert_eq!(cosine_distance(&[1.0, 2.0], &[2.0, 4.0]), Some(0.0));
+        assert_eq!(cosine_distance(&[1.0, 0.0], &[0.0, 5.0]), Some(1.0));
+        assert_eq!(cosine_distance(&[1.0, 0.0], &[-2.0, 0.0]), Some(2.0));
+        assert_eq!(cosine_distance(&[1.0, 0.0], &[1.0, 0.0, 0.0]), None);
+        assert_eq!(cosine_distance(&[0.0, 0.0], &[1.0, 0.0]), None);
+    }
+
+    #[test]
+    fn fused_similarity_mixes_appearance_and_iou() {
+        let mut track = KalmanBoxTracker::new(&[0.0, 0.0, 10.0, 10.0], 0);
+        track.update_appearance(&[1.0, 0.0], GalleryMode::Ema { momentum: 0.9 });
+        let tracks = [track];
+        let predicted_boxes = [[0.0, 0.0, 10.0, 10.0]];
+        let same = [0.0, 0.0, 10.0, 10.0];
+        // IoU 0.5 with the predicted box.
+        let taller = [0.0, 0.0, 10.0, 20.0];
+        let detections = [
+            Detection::new(same, 0, 0.9).with_embedding(vec![1.0, 0.0]),
+            Detection::new(taller, 0, 0.9).with_embedding(vec![1.0, 0.0]),
+            Detection::new(same, 0, 0.9).with_embedding(vec![0.0, 1.0]),
+            Detection::new(taller, 0, 0.9),
+            Detection::new([50.0, 50.0, 60.0, 60.0], 0, 0.9),
+            Detection::new(same, 1, 0.9).with_embedding(vec![1.0, 0.0]),
+        ];
+        let inf = f64::INFINITY;
+        let fused = |config: &AppearanceConfig, class_aware| {
+            fused_similarity(
+                config,
+                &tracks,
+                &predicted_boxes,
+                &detections,
+                0.3,
+                class_aware,
+            )
+        };
+
+        let config = AppearanceConfig::default();
+        assert_eq!(
+            fused(&config, false).as_slice(),
+            [1.0, 0.75, -inf, 0.5, -inf, 1.0]
+        );
+        assert_eq!(
+            fused(&config, true).as_slice(),
+            [1.0, 0.75, -inf, 0.5, -inf, -inf]
+        );
+
+        let near = config.with_minimum_proximity_iou(0.6);
+        assert_eq!(
+            fused(&near, false).as_slice(),
+            [1.0, -inf, -inf, 0.5, -inf, 1.0]
+        );
+    }
+}
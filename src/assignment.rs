@@ -0,0 +1,371 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Result, TrackerError};
+use crate::float::Float;
+use crate::utils::{linear_sum_assignment, solve_assignment, LsapWorkspace};
+
+/// Algorithm solving the track-detection assignment of each association
+/// stage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AssignmentSolver {
+    /// Dense shortest augmenting path over the full matrix, as
+    /// `scipy.optimize.linear_sum_assignment` in the Python package.
+    #[default]
+    Exact,
+    /// Takes pairs within the cost limit in ascending cost order. Fastest,
+    /// but not optimal when several tracks compete for a detection.
+    Greedy,
+    /// Optimal assignment where leaving a track or detection unmatched costs
+    /// half the cost limit, as `lap.lapjv(extend_cost=True, cost_limit=...)`
+    /// in the original ByteTrack. Only pairs within the limit are evaluated,
+    /// one connected group of tracks and detections at a time. Unlike
+    /// `Exact`, a pair outside the limit never displaces one inside it.
+    Sparse,
+}
+
+impl AssignmentSolver {
+    /// Assigns rows to columns of the row-major `n_rows x n_cols`
+    /// `cost_matrix`, minimizing total cost. `Greedy` and `Sparse` never
+    /// assign pairs costing more than `cost_limit`; `Exact` ignores it, so
+    /// callers filter its pairs themselves.
+    ///
+    /// Returns the assigned column for every row, `None` for unassigned rows.
+    /// `Exact` fails on invalid or infeasible matrices like
+    /// [`linear_sum_assignment`](crate::linear_sum_assignment).
+    pub fn solve<F: Float>(
+        &self,
+        cost_matrix: &[F],
+        n_rows: usize,
+        n_cols: usize,
+        cost_limit: F,
+    ) -> Result<Vec<Option<usize>>> {
+        if cost_matrix.len() != n_rows * n_cols {
+            return Err(TrackerError::CostMatrixShape {
+                len: cost_matrix.len(),
+                n_rows,
+                n_cols,
+            });
+        }
+
+        match self {
+            Self::Exact => linear_sum_assignment(cost_matrix, n_rows, n_cols),
+            Self::Greedy | Self::Sparse => {
+                Ok(self.solve_unchecked(cost_matrix, n_rows, n_cols, cost_limit))
+            }
+        }
+    }
+
+    /// Writes the assignment to `assignment`, reusing `workspace`. Only
+    /// `Exact` is allocation-free once the buffers have grown to the problem
+    /// size; `Greedy` and `Sparse` allocate their pairs, groups and result on
+    /// every call.
+    pub(crate) fn solve_into<F: Float>(
+        &self,
+        cost_matrix: &[F],
+        n_rows: usize,
+        n_cols: usize,
+        cost_limit: F,
+        workspace: &mut LsapWorkspace<F>,
+        assignment: &mut Vec<Option<usize>>,
+    ) {
+        match self {
+            Self::Exact => {
+                workspace.solve_unchecked(cost_matrix, n_rows, n_cols, assignment);
+            }
+            Self::Greedy | Self::Sparse => {
+                *assignment = self.solve_unchecked(cost_matrix, n_rows, n_cols, cost_limit);
+            }
+        }
+    }
+
+    pub(crate) fn solve_unchecked<F: Float>(
+        &self,
+        cost_matrix: &[F],
+        n_rows: usize,
+        n_cols: usize,
+        cost_limit: F,
+    ) -> Vec<Option<usize>> {
+        match self {
+            Self::Exact => solve_assignment(cost_matrix, n_rows, n_cols),
+            Self::Greedy => solve_greedy(cost_matrix, n_rows, n_cols, cost_limit),
+            Self::Sparse => solve_sparse(cost_matrix, n_rows, n_cols, cost_limit),
+        }
+    }
+}
+
+fn solve_greedy<F: Float>(
+    cost_matrix: &[F],
+    n_rows: usize,
+    n_cols: usize,
+    cost_limit: F,
+) -> Vec<Option<usize>> {
+    let mut pairs: Vec<(usize, usize)> = (0..n_rows * n_cols)
+        .filter(|&k| cost_matrix[k] <= cost_limit)
+        .map(|k| (k / n_cols, k % n_cols))
+        .collect();
+    // Stable sort keeps row-major order for equal costs.
+    pairs.sort_by(|a, b| {
+        let cost_a = cost_matrix[a.0 * n_cols + a.1];
+        let cost_b = cost_matrix[b.0 * n_cols + b.1];
+        cost_a
+            .partial_cmp(&cost_b)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let mut assignment = vec![None; n_rows];
+    let mut used_cols = vec![false; n_cols];
+    for (row, col) in pairs {
+        if assignment[row].is_none() && !used_cols[col] {
+            assignment[row] = Some(col);
+            used_cols[col] = true;
+        }
+    }
+
+    assignment
+}
+
+fn solve_sparse<F: Float>(
+    cost_matrix: &[F],
+    n_rows: usize,
+    n_cols: usize,
+    cost_limit: F,
+) -> Vec<Option<usize>> {
+    let mut assignment = vec![None; n_rows];
+
+    let pairs = (0..n_rows * n_cols)
+        .filter(|&k| cost_matrix[k] <= cost_limit)
+        .map(|k| (k / n_cols, k % n_cols));
+    let groups = connected_groups(n_rows, n_cols, pairs);
+
+    for (rows, cols) in &groups {
+        if rows.is_empty() || cols.is_empty() {
+            continue;
+        }
+        if rows.len() == 1 && cols.len() == 1 {
+            assignment[rows[0]] = Some(cols[0]);
+            continue;
+        }
+        solve_group(cost_matrix, n_cols, rows, cols, cost_limit, &mut assignment);
+    }
+
+    assignment
+}
+
+/// Solves one group of the sparse problem as a square assignment extended
+/// with a dummy partner per row and column.
+fn solve_group<F: Float>(
+    cost_matrix: &[F],
+    n_cols: usize,
+    rows: &[usize],
+    cols: &[usize],
+    cost_limit: F,
+    assignment: &mut [Option<usize>],
+) {
+    let (r, c) = (rows.len(), cols.len());
+    let size = r + c;
+
+    let mut max_abs = cost_limit.abs();
+    for &row in rows {
+        for &col in cols {
+            let cost = cost_matrix[row * n_cols + col];
+            if cost <= cost_limit {
+                max_abs = max_abs.max(cost.abs());
+            }
+        }
+    }
+    // Exceeds the cost difference of any two assignments without it.
+    let forbidden = (max_abs * F::of_f64(2.0) + F::one()) * F::of_f64(size as f64 + 1.0);
+    let unmatched = cost_limit / F::of_f64(2.0);
+
+    let mut extended = vec![forbidden; size * size];
+    for (i, &row) in rows.iter().enumerate() {
+        for (j, &col) in cols.iter().enumerate() {
+            let cost = cost_matrix[row * n_cols + col];
+            if cost <= cost_limit {
+                extended[i * size + j] = cost;
+            }
+        }
+        extended[i * size + c + i] = unmatched;
+    }
+    for j in 0..c {
+        extended[(r + j) * size + j] = unmatched;
+        for i in 0..r {
+            extended[(r + j) * size + c + i] = F::zero();
+        }
+    }
+
+    for (i, opt_col) in solve_assignment(&extended, size, size)
+        .into_iter()
+        .take(r)
+        .enumerate()
+    {
+        if let Some(j) = opt_col.filter(|&j| j < c) {
+            assignment[rows[i]] = Some(cols[j]);
+        }
+    }
+}
+
+/// Splits rows and columns into groups connected by `pairs`, each listing
+/// its rows and columns in ascending order. Rows or columns without a pair
+/// form groups of their own.
+pub(crate) fn connected_groups(
+    n_rows: usize,
+    n_cols: usize,
+    pairs: impl IntoIterator<Item = (usize, usize)>,
+) -> Vec<(Vec<usize>, Vec<usize>)> {
+    // Row `i` is node `i` and column `j` node `n_rows + j`.
+    let mut parent: Vec<usize> = (0..n_rows + n_cols).collect();
+    for (row, col) in pairs {
+        let a = find_root(&mut parent, row);
+        let b = find_root(&mut parent, n_rows + col);
+        parent[a] = b;
+    }
+
+    let mut groups: Vec<(Vec<usize>, Vec<usize>)> = Vec::new();
+    let mut group_of_root = vec![usize::MAX; n_rows + n_cols];
+    for node in 0..n_rows + n_cols {
+        let root = find_root(&mut parent, node);
+        if group_of_root[root] == usize::MAX {
+            group_of_root[root] = groups.len();
+            groups.push((Vec::new(), Vec::new()));
+        }
+        let group = &mut groups[group_of_root[root]];
+        if node < n_rows {
+            group.0.push(node);
+        } else {
+            group.1.push(node - n_rows);
+        }
+    }
+
+    groups
+}
+
+fn find_root(parent: &mut [usize], mut node: usize) -> usize {
+    while parent[node] != node {
+        parent[node] = parent[parent[node]];
+        node = parent[node];
+    }
+    node
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn next(state: &mut u64) -> f64 {
+        *state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (*state >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// `Sparse` leaves a row or column unmatched at half the cost limit,
+    /// so it maximizes the total `cost_limit - cost` over pairs within the
+    /// limit. `Exact` on costs shifted by the limit, with pairs outside it
+    /// at zero, solves the same problem.
+    fn exact_within_limit(
+        cost_matrix: &[f64],
+        n_rows: usize,
+        n_cols: usize,
+        cost_limit: f64,
+    ) -> Vec<Option<usize>> {
+        let shifted: Vec<f64> = cost_matrix
+            .iter()
+            .map(|&cost| (cost - cost_limit).min(0.0))
+            .collect();
+        AssignmentSolver::Exact
+            .solve(&shifted, n_rows, n_cols, cost_limit)
+            .unwrap()
+            .into_iter()
+            .enumerate()
+            .map(|(row, col)| col.filter(|&col| cost_matrix[row * n_cols + col] <= cost_limit))
+            .collect()
+    }
+
+    #[test]
+    fn sparse_matches_exact_within_the_cost_limit() {
+        let mut state = 5u64;
+        for round in 0..300 {
+            let n_rows = 1 + round % 9;
+            let n_cols = 1 + round % 7;
+            let cost_limit = next(&mut state) * 1.2 - 0.6;
+            let cost: Vec<f64> = (0..n_rows * n_cols)
+                .map(|_| next(&mut state) * 2.0 - 1.0)
+                .collect();
+            assert_eq!(
+                AssignmentSolver::Sparse
+                    .solve(&cost, n_rows, n_cols, cost_limit)
+                    .unwrap(),
+                exact_within_limit(&cost, n_rows, n_cols, cost_limit),
+                "round {round}"
+            );
+        }
+    }
+
+    #[test]
+    fn greedy_breaks_ties_in_row_major_order() {
+        let cost = [0.3, 0.3, 0.3, 0.9];
+        // Taking (0, 1) first would have left column 0 to row 1.
+        assert_eq!(
+            AssignmentSolver::Greedy.solve(&cost, 2, 2, 0.5).unwrap(),
+            [Some(0), None]
+        );
+        assert_eq!(
+            AssignmentSolver::Sparse.solve(&cost, 2, 2, 0.5).unwrap(),
+            [Some(1), Some(0)]
+        );
+    }
+
+    #[test]
+    fn greedy_is_suboptimal_when_tracks_compete() {
+        // Both tracks prefer detection 0, which greedy gives to track 0.
+        let cost = [0.1, 0.2, 0.15, 0.9];
+        assert_eq!(
+            AssignmentSolver::Greedy.solve(&cost, 2, 2, 1.0).unwrap(),
+            [Some(0), Some(1)]
+        );
+        for solver in [AssignmentSolver::Exact, AssignmentSolver::Sparse] {
+            assert_eq!(
+                solver.solve(&cost, 2, 2, 1.0).unwrap(),
+                [Some(1), Some(0)],
+                "{solver:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn negative_cost_limit_keeps_pairs_below_it() {
+        // Negated similarities with a minimum similarity of 0.1.
+        let cost = [-0.05, -0.3, -0.2, -0.08, -0.09, -0.01];
+        for solver in [AssignmentSolver::Greedy, AssignmentSolver::Sparse] {
+            assert_eq!(
+                solver.solve(&cost, 3, 2, -0.1).unwrap(),
+                [Some(1), Some(0), None],
+                "{solver:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn solve_into_matches_solve() {
+        let mut state = 9u64;
+        let mut workspace = LsapWorkspace::new();
+        let mut assignment = Vec::new();
+        for solver in [
+            AssignmentSolver::Exact,
+            AssignmentSolver::Greedy,
+            AssignmentSolver::Sparse,
+        ] {
+            for (n_rows, n_cols) in [(12, 8), (3, 5), (0, 2)] {
+                let cost: Vec<f64> = (0..n_rows * n_cols).map(|_| next(&mut state)).collect();
+                solver.solve_into(&cost, n_rows, n_cols, 0.5, &mut workspace, &mut assignment);
+                assert_eq!(
+                    assignment,
+                    solver.solve(&cost, n_rows, n_cols, 0.5).unwrap(),
+                    "{solver:?}"
+                );
+            }
+        }
+    }
+}
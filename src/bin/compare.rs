@@ -1,35 +1,36 @@
-mod detection;
-mod iou;
-mod kalman;
-mod tracker;
-mod utils;
-
-use anyhow::{Context, Result};
-use detection::{InputData, OutputData};
+use anyhow::{bail, Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use std::fs;
-use tracker::ByteTrackTracker;
+use trackers_rs::{
+    BotSortConfig, ByteTrackConfig, Float, InputData, OcSortConfig, OutputData, SortConfig,
+    TrackerConfig,
+};
 
-fn load_detections(path: &str) -> Result<InputData> {
+fn load_detections<F: Float + DeserializeOwned>(path: &str) -> Result<InputData<F>> {
     let content = fs::read_to_string(path)
         .with_context(|| format!("Failed to read detections file: {}", path))?;
 
-    let data: InputData =
+    let data: InputData<F> =
         serde_json::from_str(&content).with_context(|| "Failed to parse detections JSON")?;
 
     Ok(data)
 }
 
-fn load_tracked_json(path: &str) -> Result<OutputData> {
+fn load_tracked_json<F: Float + DeserializeOwned>(path: &str) -> Result<OutputData<F>> {
     let content = fs::read_to_string(path)
         .with_context(|| format!("Failed to read tracked file: {}", path))?;
 
-    let data: OutputData =
+    let data: OutputData<F> =
         serde_json::from_str(&content).with_context(|| "Failed to parse tracked JSON")?;
 
     Ok(data)
 }
 
-fn compare_outputs(rust_output: &OutputData, python_output: &OutputData) -> Result<bool> {
+fn compare_outputs<F: Float>(
+    rust_output: &OutputData<F>,
+    python_output: &OutputData<F>,
+) -> Result<bool> {
     let mut all_match = true;
 
     if rust_output.total_frames != python_output.total_frames {
@@ -49,8 +50,6 @@ fn compare_outputs(rust_output: &OutputData, python_output: &OutputData) -> Resu
         all_match = false;
     }
 
-    let tolerance = 1e-6;
-
     for (frame_idx, (rust_frame, python_frame)) in rust_output
         .detections
         .iter()
@@ -85,7 +84,7 @@ fn compare_outputs(rust_output: &OutputData, python_output: &OutputData) -> Resu
                 .zip(python_det.box_coords.iter())
                 .enumerate()
             {
-                if (r_coord - p_coord).abs() > tolerance {
+                if !coords_match(r_coord, p_coord) {
                     println!(
                         "Frame {}, Detection {}, Coord {}: box mismatch: rust={}, python={}",
                         frame_idx, det_idx, coord_idx, r_coord, p_coord
@@ -99,29 +98,66 @@ fn compare_outputs(rust_output: &OutputData, python_output: &OutputData) -> Resu
     Ok(all_match)
 }
 
+/// Whether two coordinates agree to a few ulps of `F`, so that an `f64` run
+/// is held to a float64 reference and not to its `f32` rounding.
+fn coords_match<F: Float>(rust: F, python: F) -> bool {
+    let (rust, python) = (rust.as_f64(), python.as_f64());
+    let ulp = F::default_epsilon().as_f64() * rust.abs().max(python.abs());
+    (rust - python).abs() <= 4.0 * ulp
+}
+
+/// Config and `data/tracked_*.json` file prefix of the tracker named by the
+/// first command line argument.
+fn tracker_config(arg: Option<&str>) -> Result<(TrackerConfig, &'static str)> {
+    match arg.unwrap_or("bytetrack") {
+        "bytetrack" => {
+            let config = ByteTrackConfig::default().with_track_activation_threshold(0.25);
+            Ok((TrackerConfig::ByteTrack(config), ""))
+        }
+        "sort" => Ok((TrackerConfig::Sort(SortConfig::default()), "sort_")),
+        "ocsort" => Ok((TrackerConfig::OcSort(OcSortConfig::default()), "ocsort_")),
+        "botsort" => Ok((TrackerConfig::BotSort(BotSortConfig::default()), "botsort_")),
+        other => bail!(
+            "Unknown tracker: {} (expected bytetrack, sort, ocsort or botsort)",
+            other
+        ),
+    }
+}
+
 fn main() -> Result<()> {
+    let arg = std::env::args().nth(1);
+    let (config, prefix) = tracker_config(arg.as_deref())?;
+
+    // The optional second argument selects the precision, `f32` by default.
+    // `f64` needs a reference written by Python at float64 precision.
+    match std::env::args().nth(2).as_deref().unwrap_or("f32") {
+        "f32" => run::<f32>(&config, prefix),
+        "f64" => run::<f64>(&config, prefix),
+        other => bail!("Unknown precision: {} (expected f32 or f64)", other),
+    }
+}
+
+fn run<F: Float + Serialize + DeserializeOwned>(
+    config: &TrackerConfig,
+    prefix: &str,
+) -> Result<()> {
+    let mut tracker = config.build::<F>()?;
+    let rust_path = format!("data/tracked_{}rs.json", prefix);
+    let python_path = format!("data/tracked_{}py.json", prefix);
+
     println!("Loading detections from data/detections.json...");
 
-    let input_data = load_detections("data/detections.json")?;
+    let input_data = load_detections::<F>("data/detections.json")?;
 
     println!("Total frames: {}", input_data.total_frames);
-    println!("Processing frames with ByteTrack...");
-
-    let mut tracker = ByteTrackTracker::new(
-        30,   // lost_track_buffer
-        30.0, // frame_rate
-        0.25, // track_activation_threshold
-        2,    // minimum_consecutive_frames
-        0.1,  // minimum_iou_threshold
-        0.6,  // high_conf_det_threshold
-    );
+    println!("Processing frames with {}...", tracker.name());
 
     let mut tracked_results = Vec::new();
     let mut update_times = Vec::new();
 
     for (frame_idx, frame_detections) in input_data.detections.iter().enumerate() {
         let start = std::time::Instant::now();
-        let tracked = tracker.update(frame_detections);
+        let tracked = tracker.update(frame_detections)?;
         let duration = start.elapsed();
         update_times.push(duration.as_secs_f64());
 
@@ -146,7 +182,7 @@ fn main() -> Result<()> {
 
     let output_data = OutputData {
         source_file: "data/detections.json".to_string(),
-        tracker: "ByteTrack".to_string(),
+        tracker: tracker.name().to_string(),
         total_frames: input_data.total_frames,
         avg_performance_ms: Some(avg_time),
         detections: tracked_results,
@@ -155,14 +191,21 @@ fn main() -> Result<()> {
     let output_json =
         serde_json::to_string_pretty(&output_data).context("Failed to serialize output JSON")?;
 
-    fs::write("data/tracked_rs.json", &output_json).context("Failed to write tracked_rs.json")?;
+    fs::write(&rust_path, &output_json)
+        .with_context(|| format!("Failed to write {}", rust_path))?;
 
-    println!("Saved tracked results to data/tracked_rs.json");
+    println!("Saved tracked results to {}", rust_path);
     println!("Average tracker update time: {:.4} ms", avg_time);
 
-    println!("Comparing with Python output from data/tracked_py.json...");
+    // Trackers without a Python reference run (e.g. OC-SORT) can only be inspected.
+    if !std::path::Path::new(&python_path).exists() {
+        println!("No Python output at {}, skipping comparison.", python_path);
+        return Ok(());
+    }
+
+    println!("Comparing with Python output from {}...", python_path);
 
-    let python_output = load_tracked_json("data/tracked_py.json")?;
+    let python_output = load_tracked_json::<F>(&python_path)?;
 
     let matches = compare_outputs(&output_data, &python_output)?;
 
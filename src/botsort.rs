@@ -0,0 +1,112 @@
+use crate::bytetrack::ByteTrackTracker;
+use crate::cmc::CameraMotion;
+use crate::config::BotSortConfig;
+use crate::cost::AssociationCost;
+use crate::detection::{Detection, TrackedDetection};
+use crate::error::Result;
+use crate::events::TrackEvent;
+use crate::float::Float;
+use crate::tracker::{TrackInfo, Tracker, TrackerConfig};
+
+/// BoT-SORT tracker: ByteTrack association on an XYWH Kalman state (by
+/// default) with size-proportional noise, score-fused IoU in the first stage
+/// and camera motion compensation supplied by the caller.
+pub struct BotSortTracker<F: Float = f32> {
+    config: BotSortConfig,
+    inner: ByteTrackTracker<F>,
+}
+
+impl<F: Float> BotSortTracker<F> {
+    /// Creates a tracker after validating `config`.
+    pub fn new(config: BotSortConfig) -> Result<Self> {
+        let inner =
+            ByteTrackTracker::new(config.to_bytetrack())?.with_fuse_score(config.fuse_score);
+        Ok(Self { config, inner })
+    }
+
+    pub fn config(&self) -> &BotSortConfig {
+        &self.config
+    }
+
+    /// See [`ByteTrackTracker::with_first_stage_cost`].
+    pub fn with_first_stage_cost(mut self, cost: impl AssociationCost<F> + 'static) -> Self {
+        self.inner = self.inner.with_first_stage_cost(cost);
+        self
+    }
+
+    /// See [`ByteTrackTracker::with_second_stage_cost`].
+    pub fn with_second_stage_cost(mut self, cost: impl AssociationCost<F> + 'static) -> Self {
+        self.inner = self.inner.with_second_stage_cost(cost);
+        self
+    }
+
+    /// Advances the tracker by one frame. `camera_motion` maps the previous
+    /// frame onto this one and is applied to every track after prediction;
+    /// pass `None` for a static camera. Camera motion that cannot warp every
+    /// track returns
+    /// [`TrackerError::InvalidCameraMotion`](crate::TrackerError::InvalidCameraMotion)
+    /// before any state changes.
+    pub fn update(
+        &mut self,
+        detections: &[Detection<F>],
+        camera_motion: Option<&CameraMotion<F>>,
+    ) -> Result<Vec<TrackedDetection<F>>> {
+        self.inner
+            .update_with_camera_motion(detections, camera_motion)
+    }
+
+    /// Same as [`update`](Self::update), writing to `output` without heap
+    /// allocations in steady state, see [`ByteTrackTracker::update_into`].
+    pub fn update_into(
+        &mut self,
+        detections: &[Detection<F>],
+        camera_motion: Option<&CameraMotion<F>>,
+        output: &mut Vec<TrackedDetection<F>>,
+    ) -> Result<()> {
+        self.inner
+            .update_with_camera_motion_into(detections, camera_motion, output)
+    }
+}
+
+impl<F: Float> Tracker<F> for BotSortTracker<F> {
+    fn name(&self) -> &'static str {
+        "BoT-SORT"
+    }
+
+    /// Updates without camera motion compensation.
+    fn update(&mut self, detections: &[Detection<F>]) -> Result<Vec<TrackedDetection<F>>> {
+        BotSortTracker::update(self, detections, None)
+    }
+
+    fn update_into(
+        &mut self,
+        detections: &[Detection<F>],
+        output: &mut Vec<TrackedDetection<F>>,
+    ) -> Result<()> {
+        BotSortTracker::update_into(self, detections, None, output)
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+    }
+
+    fn active_tracks(&self) -> Vec<TrackInfo<F>> {
+        self.inner.active_tracks()
+    }
+
+    fn lost_tracks(&self) -> Vec<TrackInfo<F>> {
+        self.inner.lost_tracks()
+    }
+
+    fn removed_tracks(&self) -> Vec<TrackInfo<F>> {
+        self.inner.removed_tracks()
+    }
+
+    fn events(&self) -> &[TrackEvent<F>] {
+        self.inner.events()
+    }
+
+    fn config(&self) -> TrackerConfig {
+        TrackerConfig::BotSort(self.config.clone())
+    }
+}
@@ -0,0 +1,1011 @@
+use crate::appearance::fused_similarity;
+use crate::cmc::CameraMotion;
+use crate::config::ByteTrackConfig;
+use crate::cost::{AssociationCost, AssociationInput};
+use crate::detection::{check_detections, Detection, TrackedDetection};
+use crate::error::{Result, TrackerError};
+use crate::events::{record_events, TrackEvent};
+use crate::float::Float;
+use crate::kalman::KalmanBoxTracker;
+use crate::snapshot::ByteTrackSnapshot;
+use crate::tracker::{active_tracks, lost_tracks, TrackInfo, Tracker, TrackerConfig};
+use crate::utils::{prune_tracks, sparse_associated_indices, AssociationWorkspace, Matches};
+
+pub struct ByteTrackTracker<F: Float = f32> {
+    config: ByteTrackConfig,
+    maximum_frames_without_update: i32,
+    next_tracker_id: i32,
+    frame_count: u64,
+    // BoT-SORT option, see `BotSortTracker`
+    fuse_score: bool,
+    // Replace the built-in association, see `with_first_stage_cost`
+    first_stage_cost: Option<Box<dyn AssociationCost<F>>>,
+    second_stage_cost: Option<Box<dyn AssociationCost<F>>>,
+
+    // Buffers for memory reuse
+    tracks: Vec<KalmanBoxTracker<F>>,
+    /// Tracks at the start of the frame, restored when it fails.
+    saved_tracks: Vec<KalmanBoxTracker<F>>,
+    track_indices: Vec<usize>,
+    high_conf_detections: Vec<Detection<F>>,
+    low_conf_detections: Vec<Detection<F>>,
+    high_conf_indices: Vec<usize>,
+    low_conf_indices: Vec<usize>,
+    high_conf_boxes: Vec<[F; 4]>,
+    low_conf_boxes: Vec<[F; 4]>,
+    high_conf_classes: Vec<i32>,
+    low_conf_classes: Vec<i32>,
+    high_conf_scores: Vec<F>,
+    predicted_boxes: Vec<[F; 4]>,
+    remaining_predicted_boxes: Vec<[F; 4]>,
+    track_classes: Vec<i32>,
+    remaining_track_classes: Vec<i32>,
+    gate: Vec<bool>,
+    association: AssociationWorkspace<F>,
+    first_stage_matches: Matches,
+    second_stage_matches: Matches,
+    matched_indices_adjusted: Vec<(usize, usize)>,
+    removed_tracks: Vec<KalmanBoxTracker<F>>,
+    events: Vec<TrackEvent<F>>,
+}
+
+impl<F: Float> ByteTrackTracker<F> {
+    /// Creates a tracker after validating `config`.
+    pub fn new(config: ByteTrackConfig) -> Result<Self> {
+        config.validate()?;
+
+        Ok(Self {
+            maximum_frames_without_update: config.maximum_frames_without_update(),
+            config,
+            next_tracker_id: 0,
+            frame_count: 0,
+            fuse_score: false,
+            first_stage_cost: None,
+            second_stage_cost: None,
+            tracks: Vec::new(),
+            saved_tracks: Vec::new(),
+            track_indices: Vec::new(),
+            high_conf_detections: Vec::new(),
+            low_conf_detections: Vec::new(),
+            high_conf_indices: Vec::new(),
+            low_conf_indices: Vec::new(),
+            high_conf_boxes: Vec::new(),
+            low_conf_boxes: Vec::new(),
+            high_conf_classes: Vec::new(),
+            low_conf_classes: Vec::new(),
+            high_conf_scores: Vec::new(),
+            predicted_boxes: Vec::new(),
+            remaining_predicted_boxes: Vec::new(),
+            track_classes: Vec::new(),
+            remaining_track_classes: Vec::new(),
+            gate: Vec::new(),
+            association: AssociationWorkspace::default(),
+            first_stage_matches: Matches::default(),
+            second_stage_matches: Matches::default(),
+            matched_indices_adjusted: Vec::new(),
+            removed_tracks: Vec::new(),
+            events: Vec::new(),
+        })
+    }
+
+    pub fn config(&self) -> &ByteTrackConfig {
+        &self.config
+    }
+
+    /// Copies the complete tracker state, so that it can be persisted and
+    /// [`restore`](Self::restore)d later, e.g. after a restart.
+    pub fn snapshot(&self) -> ByteTrackSnapshot<F> {
+        ByteTrackSnapshot::new(
+            self.config.clone(),
+            self.next_tracker_id,
+            self.frame_count,
+            self.tracks.iter().map(KalmanBoxTracker::snapshot).collect(),
+            self.removed_tracks
+                .iter()
+                .map(KalmanBoxTracker::snapshot)
+                .collect(),
+            self.events.clone(),
+        )
+    }
+
+    /// Recreates the tracker a snapshot was taken of. It continues with the
+    /// same track IDs and returns the same output as the original tracker
+    /// would have.
+    ///
+    /// Stage costs set by [`with_first_stage_cost`](Self::with_first_stage_cost)
+    /// are not part of the snapshot and have to be set again.
+    pub fn restore(snapshot: ByteTrackSnapshot<F>) -> Result<Self> {
+        let mut tracker = Self::new(snapshot.config)?;
+        tracker.next_tracker_id = snapshot.next_tracker_id;
+        tracker.frame_count = snapshot.frame_count;
+        tracker.tracks = snapshot
+            .tracks
+            .into_iter()
+            .map(KalmanBoxTracker::from_snapshot)
+            .collect();
+        tracker.removed_tracks = snapshot
+            .removed_tracks
+            .into_iter()
+            .map(KalmanBoxTracker::from_snapshot)
+            .collect();
+        tracker.events = snapshot.events;
+        Ok(tracker)
+    }
+
+    pub(crate) fn with_fuse_score(mut self, fuse_score: bool) -> Self {
+        self.fuse_score = fuse_score;
+        self
+    }
+
+    /// Matches all tracks with high-confidence detections by `cost` instead
+    /// of IoU, appearance and score fusion from the config. Class-aware mode
+    /// still gates pairs of different classes.
+    ///
+    /// Costs are not part of [`ByteTrackConfig`] and have to be set again on
+    /// a tracker built from a config.
+    pub fn with_first_stage_cost(mut self, cost: impl AssociationCost<F> + 'static) -> Self {
+        self.first_stage_cost = Some(Box::new(cost));
+        self
+    }
+
+    /// Matches the tracks left unmatched by the first stage with
+    /// low-confidence detections by `cost` instead of IoU, like
+    /// [`with_first_stage_cost`](Self::with_first_stage_cost).
+    pub fn with_second_stage_cost(mut self, cost: impl AssociationCost<F> + 'static) -> Self {
+        self.second_stage_cost = Some(Box::new(cost));
+        self
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn update_detections(
+        tracks: &mut [KalmanBoxTracker<F>],
+        detections: &[Detection<F>],
+        detection_indices: &[usize],
+        predicted_boxes: &[[F; 4]],
+        output: &mut Vec<TrackedDetection<F>>,
+        matched_indices: &[(usize, usize)],
+        config: &ByteTrackConfig,
+        next_tracker_id: &mut i32,
+    ) -> Result<()> {
+        for &(track_idx, det_idx) in matched_indices {
+            let detection = &detections[det_idx];
+            tracks[track_idx].update_with_score(&detection.box_coords, Some(detection.score))?;
+            tracks[track_idx].update_class(detection.class_id, config.track_class_mode);
+            if let (Some(appearance), Some(embedding)) = (&config.appearance, &detection.embedding)
+            {
+                tracks[track_idx].update_appearance(embedding, appearance.gallery);
+            }
+
+            if tracks[track_idx].number_of_successful_updates >= config.minimum_consecutive_frames
+                && tracks[track_idx].tracker_id == -1
+            {
+                tracks[track_idx].confirm(*next_tracker_id);
+                *next_tracker_id += 1;
+            }
+
+            output.push(TrackedDetection::new(
+                detection,
+                detection_indices[det_idx],
+                tracks[track_idx].tracker_id,
+                Some(predicted_boxes[track_idx]),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Matches tracks to detections. Pairs of different classes are forbidden
+    /// when the tracker is class-aware.
+    ///
+    /// With `detection_scores`, IoU is multiplied by detection score before
+    /// assignment, as BoT-SORT does in its first stage. Pairs set in the
+    /// row-major `gate` are never matched.
+    #[allow(clippy::too_many_arguments)]
+    fn associate(
+        config: &ByteTrackConfig,
+        track_boxes: &[[F; 4]],
+        track_classes: &[i32],
+        detection_boxes: &[[F; 4]],
+        detection_classes: &[i32],
+        detection_scores: Option<&[F]>,
+        gate: Option<&[bool]>,
+        workspace: &mut AssociationWorkspace<F>,
+        matches: &mut Matches,
+    ) {
+        let min_similarity = F::of_f64(config.minimum_iou_threshold);
+        if config.spatial_index && !config.class_aware && gate.is_none() {
+            if let Some(mut similarity) = config
+                .iou_metric
+                .similarity_sparse(track_boxes, detection_boxes)
+            {
+                if let Some(scores) = detection_scores {
+                    similarity.scale_columns(scores);
+                }
+                return matches.set(sparse_associated_indices(
+                    config.solver,
+                    &similarity,
+                    min_similarity,
+                ));
+            }
+        }
+
+        workspace.set_metric(config.iou_metric, track_boxes, detection_boxes);
+        if let Some(scores) = detection_scores {
+            workspace.scale_columns(scores);
+        }
+        if config.class_aware {
+            workspace.forbid_other_classes(track_classes, detection_classes);
+        }
+        workspace.apply_gate(gate);
+        workspace.associate(config.solver, min_similarity, matches);
+    }
+
+    /// Matches the tracks at `track_indices` with `detections` by `cost`.
+    /// Matched track indices are positions in `track_indices`.
+    ///
+    /// Pairs of `-inf` similarity are never matched, like pairs of different
+    /// classes in class-aware mode and pairs set in the row-major `gate`.
+    #[allow(clippy::too_many_arguments)]
+    fn associate_with_cost(
+        cost: &dyn AssociationCost<F>,
+        config: &ByteTrackConfig,
+        tracks: &[KalmanBoxTracker<F>],
+        predicted_boxes: &[[F; 4]],
+        track_indices: &[usize],
+        detections: &[Detection<F>],
+        gate: Option<&[bool]>,
+        workspace: &mut AssociationWorkspace<F>,
+        matches: &mut Matches,
+    ) -> Result<()> {
+        let input = AssociationInput {
+            tracks,
+            predicted_boxes,
+            track_indices,
+            detections,
+        };
+        let similarity = cost.similarity(&input);
+        let (n_rows, n_cols) = (track_indices.len(), detections.len());
+        if similarity.shape() != (n_rows, n_cols) {
+            return Err(TrackerError::CostMatrixShape {
+                len: similarity.len(),
+                n_rows,
+                n_cols,
+            });
+        }
+        workspace.set_similarity(&similarity);
+        for row in 0..n_rows {
+            for col in 0..n_cols {
+                let value = similarity[(row, col)];
+                if value == F::of_f64(f64::NEG_INFINITY) {
+                    workspace.forbid(row, col);
+                } else if !value.is_finite() {
+                    // NaN or `+inf` similarity, i.e. NaN or `-inf` cost.
+                    return Err(TrackerError::InvalidCost { row, col });
+                }
+            }
+        }
+        if config.class_aware {
+            for (j, detection) in detections.iter().enumerate() {
+                for i in 0..track_indices.len() {
+                    if input.track(i).class_id != detection.class_id {
+                        workspace.forbid(i, j);
+                    }
+                }
+            }
+        }
+        workspace.apply_gate(gate);
+        workspace.associate(config.solver, cost.min_similarity(), matches);
+        Ok(())
+    }
+
+    /// Flags in the row-major `gate` the pairs of the tracks at
+    /// `track_indices` and `detection_boxes` whose squared Mahalanobis
+    /// distance exceeds the configured gating threshold. Returns `false`
+    /// without gating.
+    fn mahalanobis_gate(
+        config: &ByteTrackConfig,
+        tracks: &[KalmanBoxTracker<F>],
+        track_indices: &[usize],
+        detection_boxes: &[[F; 4]],
+        gate: &mut Vec<bool>,
+    ) -> Result<bool> {
+        let Some(threshold) = config.gating_threshold else {
+            return Ok(false);
+        };
+
+        let threshold = F::of_f64(threshold);
+        gate.clear();
+        for &track_idx in track_indices {
+            for det_box in detection_boxes {
+                gate.push(tracks[track_idx].mahalanobis_distance(det_box)? > threshold);
+            }
+        }
+        Ok(true)
+    }
+
+    fn split_detections(&mut self, detections: &[Detection<F>]) {
+        self.high_conf_detections.clear();
+        self.low_conf_detections.clear();
+        self.high_conf_indices.clear();
+        self.low_conf_indices.clear();
+
+        for (idx, det) in detections.iter().enumerate() {
+            if det.score >= F::of_f64(self.config.high_conf_det_threshold) {
+                self.high_conf_detections.push(det.clone());
+                self.high_conf_indices.push(idx);
+            } else {
+                self.low_conf_detections.push(det.clone());
+                self.low_conf_indices.push(idx);
+            }
+        }
+    }
+
+    /// Starts a track for every unmatched high-confidence detection scoring
+    /// at least the activation threshold.
+    fn spawn_new_trackers(&mut self, output: &mut Vec<TrackedDetection<F>>) {
+        for &det_idx in &self.first_stage_matches.unmatched_detections {
+            let detection = &self.high_conf_detections[det_idx];
+            if detection.score >= F::of_f64(self.config.track_activation_threshold) {
+                let mut new_tracker = KalmanBoxTracker::with_noise(
+                    &detection.box_coords,
+                    detection.class_id,
+                    self.config.motion_model,
+                    self.config.noise,
+                );
+                if let (Some(appearance), Some(embedding)) =
+                    (&self.config.appearance, &detection.embedding)
+                {
+                    new_tracker.update_appearance(embedding, appearance.gallery);
+                }
+                self.tracks.push(new_tracker);
+
+                output.push(TrackedDetection::new(
+                    detection,
+                    self.high_conf_indices[det_idx],
+                    -1,
+                    None,
+                ));
+            }
+        }
+    }
+
+    /// Advances the tracker by one frame. Errors are described on
+    /// [`Tracker::update`].
+    pub fn update(&mut self, detections: &[Detection<F>]) -> Result<Vec<TrackedDetection<F>>> {
+        self.update_with_camera_motion(detections, None)
+    }
+
+    /// Same as [`update`](Self::update), writing the tracked detections to
+    /// `output` after clearing it.
+    ///
+    /// Once the internal buffers and `output` have grown to the scene size,
+    /// frames are processed without heap allocations, unless
+    /// `spatial_index`, appearance, custom stage costs, a solver
+    /// other than [`AssignmentSolver::Exact`](crate::AssignmentSolver::Exact),
+    /// class voting or detection embeddings are used.
+    pub fn update_into(
+        &mut self,
+        detections: &[Detection<F>],
+        output: &mut Vec<TrackedDetection<F>>,
+    ) -> Result<()> {
+        self.update_with_camera_motion_into(detections, None, output)
+    }
+
+    /// Same as [`update`](Self::update), warping every track by the camera
+    /// motion since the previous frame before association. Camera motion is
+    /// checked along with the detections, see [`CameraMotion::linearize`].
+    pub fn update_with_camera_motion(
+        &mut self,
+        detections: &[Detection<F>],
+        camera_motion: Option<&CameraMotion<F>>,
+    ) -> Result<Vec<TrackedDetection<F>>> {
+        let mut output = Vec::new();
+        self.update_with_camera_motion_into(detections, camera_motion, &mut output)?;
+        Ok(output)
+    }
+
+    /// [`update_with_camera_motion`](Self::update_with_camera_motion) writing
+    /// to `output` like [`update_into`](Self::update_into).
+    pub fn update_with_camera_motion_into(
+        &mut self,
+        detections: &[Detection<F>],
+        camera_motion: Option<&CameraMotion<F>>,
+        output: &mut Vec<TrackedDetection<F>>,
+    ) -> Result<()> {
+        output.clear();
+        check_detections(detections, self.config.motion_model)?;
+        if let Some(motion) = camera_motion {
+            motion.validate()?;
+            for tracker in &self.tracks {
+                tracker.check_camera_motion(motion)?;
+            }
+        }
+
+        let frame_index = self.frame_count;
+        if self.tracks.is_empty() && detections.is_empty() {
+            self.frame_count += 1;
+            self.events.clear();
+            self.removed_tracks.clear();
+            return Ok(());
+        }
+
+        // Custom costs and singular covariances fail after the tracks have
+        // changed, the saved copy restores them.
+        self.saved_tracks.clone_from(&self.tracks);
+        let next_tracker_id = self.next_tracker_id;
+        if let Err(error) = self.match_detections(detections, camera_motion, output) {
+            std::mem::swap(&mut self.tracks, &mut self.saved_tracks);
+            self.next_tracker_id = next_tracker_id;
+            output.clear();
+            return Err(error);
+        }
+
+        self.frame_count += 1;
+        self.events.clear();
+        prune_tracks(
+            &mut self.tracks,
+            |track| track,
+            self.config.minimum_consecutive_frames,
+            self.maximum_frames_without_update,
+            &mut self.removed_tracks,
+        );
+
+        record_events(
+            frame_index,
+            &mut self.tracks,
+            &self.removed_tracks,
+            &mut self.events,
+        );
+
+        Ok(())
+    }
+
+    /// Predicts the tracks, associates them with `detections` in two stages
+    /// and spawns tracks for the unmatched high-confidence detections.
+    fn match_detections(
+        &mut self,
+        detections: &[Detection<F>],
+        camera_motion: Option<&CameraMotion<F>>,
+        output: &mut Vec<TrackedDetection<F>>,
+    ) -> Result<()> {
+        for tracker in &mut self.tracks {
+            tracker.predict();
+            if let Some(motion) = camera_motion {
+                tracker.apply_camera_motion(motion)?;
+            }
+        }
+
+        self.split_detections(detections);
+
+        self.high_conf_boxes.clear();
+        self.high_conf_classes.clear();
+        self.high_conf_scores.clear();
+        for d in &self.high_conf_detections {
+            self.high_conf_boxes.push(d.box_coords);
+            self.high_conf_classes.push(d.class_id);
+            self.high_conf_scores.push(d.score);
+        }
+
+        self.low_conf_boxes.clear();
+        self.low_conf_classes.clear();
+        for d in &self.low_conf_detections {
+            self.low_conf_boxes.push(d.box_coords);
+            self.low_conf_classes.push(d.class_id);
+        }
+
+        self.predicted_boxes.clear();
+        self.track_classes.clear();
+        for t in &self.tracks {
+            self.predicted_boxes.push(t.get_state_bbox());
+            self.track_classes.push(t.class_id);
+        }
+        self.track_indices.clear();
+        self.track_indices.extend(0..self.tracks.len());
+
+        if !self.high_conf_boxes.is_empty() && !self.predicted_boxes.is_empty() {
+            let gated = Self::mahalanobis_gate(
+                &self.config,
+                &self.tracks,
+                &self.track_indices,
+                &self.high_conf_boxes,
+                &mut self.gate,
+            )?;
+            let gate = gated.then_some(self.gate.as_slice());
+            match (&self.first_stage_cost, &self.config.appearance) {
+                (Some(cost), _) => Self::associate_with_cost(
+                    cost.as_ref(),
+                    &self.config,
+                    &self.tracks,
+                    &self.predicted_boxes,
+                    &self.track_indices,
+                    &self.high_conf_detections,
+                    gate,
+                    &mut self.association,
+                    &mut self.first_stage_matches,
+                )?,
+                (None, Some(appearance)) => {
+                    let similarity = fused_similarity(
+                        appearance,
+                        &self.tracks,
+                        &self.predicted_boxes,
+                        &self.high_conf_detections,
+                        self.config.minimum_iou_threshold,
+                        self.config.class_aware,
+                    );
+                    self.association.set_similarity(&similarity);
+                    for row in 0..similarity.nrows() {
+                        for col in 0..similarity.ncols() {
+                            if similarity[(row, col)] == F::of_f64(f64::NEG_INFINITY) {
+                                self.association.forbid(row, col);
+                            }
+                        }
+                    }
+                    self.association.apply_gate(gate);
+                    // Every pair left is acceptable.
+                    self.association.associate(
+                        self.config.solver,
+                        F::zero(),
+                        &mut self.first_stage_matches,
+                    );
+                }
+                (None, None) => Self::associate(
+                    &self.config,
+                    &self.predicted_boxes,
+                    &self.track_classes,
+                    &self.high_conf_boxes,
+                    &self.high_conf_classes,
+                    self.fuse_score.then_some(self.high_conf_scores.as_slice()),
+                    gate,
+                    &mut self.association,
+                    &mut self.first_stage_matches,
+                ),
+            }
+        } else {
+            self.first_stage_matches
+                .set_unmatched(self.tracks.len(), self.high_conf_boxes.len());
+        }
+
+        Self::update_detections(
+            &mut self.tracks,
+            &self.high_conf_detections,
+            &self.high_conf_indices,
+            &self.predicted_boxes,
+            output,
+            &self.first_stage_matches.pairs,
+            &self.config,
+            &mut self.next_tracker_id,
+        )?;
+
+        let unmatched_track_indices = &self.first_stage_matches.unmatched_tracks;
+        self.remaining_predicted_boxes.clear();
+        self.remaining_track_classes.clear();
+        for &idx in unmatched_track_indices {
+            self.remaining_predicted_boxes
+                .push(self.predicted_boxes[idx]);
+            self.remaining_track_classes.push(self.track_classes[idx]);
+        }
+
+        self.matched_indices_adjusted.clear();
+        if !self.low_conf_boxes.is_empty() && !self.remaining_predicted_boxes.is_empty() {
+            let gated = Self::mahalanobis_gate(
+                &self.config,
+                &self.tracks,
+                unmatched_track_indices,
+                &self.low_conf_boxes,
+                &mut self.gate,
+            )?;
+            let gate = gated.then_some(self.gate.as_slice());
+            match &self.second_stage_cost {
+                Some(cost) => Self::associate_with_cost(
+                    cost.as_ref(),
+                    &self.config,
+                    &self.tracks,
+                    &self.predicted_boxes,
+                    unmatched_track_indices,
+                    &self.low_conf_detections,
+                    gate,
+                    &mut self.association,
+                    &mut self.second_stage_matches,
+                )?,
+                None => Self::associate(
+                    &self.config,
+                    &self.remaining_predicted_boxes,
+                    &self.remaining_track_classes,
+                    &self.low_conf_boxes,
+                    &self.low_conf_classes,
+                    None,
+                    gate,
+                    &mut self.association,
+                    &mut self.second_stage_matches,
+                ),
+            }
+            for &(i, j) in &self.second_stage_matches.pairs {
+                self.matched_indices_adjusted
+                    .push((unmatched_track_indices[i], j));
+            }
+        } else {
+            self.second_stage_matches
+                .set_unmatched(0, self.low_conf_boxes.len());
+        }
+
+        Self::update_detections(
+            &mut self.tracks,
+            &self.low_conf_detections,
+            &self.low_conf_indices,
+            &self.predicted_boxes,
+            output,
+            &self.matched_indices_adjusted,
+            &self.config,
+            &mut self.next_tracker_id,
+        )?;
+
+        for &det_idx in &self.second_stage_matches.unmatched_detections {
+            output.push(TrackedDetection::new(
+                &self.low_conf_detections[det_idx],
+                self.low_conf_indices[det_idx],
+                -1,
+                None,
+            ));
+        }
+
+        self.spawn_new_trackers(output);
+
+        Ok(())
+    }
+}
+
+impl<F: Float> Tracker<F> for ByteTrackTracker<F> {
+    fn name(&self) -> &'static str {
+        "ByteTrack"
+    }
+
+    fn update(&mut self, detections: &[Detection<F>]) -> Result<Vec<TrackedDetection<F>>> {
+        ByteTrackTracker::update(self, detections)
+    }
+
+    fn update_into(
+        &mut self,
+        detections: &[Detection<F>],
+        output: &mut Vec<TrackedDetection<F>>,
+    ) -> Result<()> {
+        ByteTrackTracker::update_into(self, detections, output)
+    }
+
+    fn reset(&mut self) {
+        self.tracks.clear();
+        self.removed_tracks.clear();
+        self.events.clear();
+        self.next_tracker_id = 0;
+        self.frame_count = 0;
+    }
+
+    fn active_tracks(&self) -> Vec<TrackInfo<F>> {
+        active_tracks(&self.tracks)
+    }
+
+    fn lost_tracks(&self) -> Vec<TrackInfo<F>> {
+        lost_tracks(&self.tracks)
+    }
+
+    fn removed_tracks(&self) -> Vec<TrackInfo<F>> {
+        self.removed_tracks.iter().map(TrackInfo::from).collect()
+    }
+
+    fn events(&self) -> &[TrackEvent<F>] {
+        &self.events
+    }
+
+    fn config(&self) -> TrackerConfig {
+        TrackerConfig::ByteTrack(self.config.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::appearance::AppearanceConfig;
+    use crate::config::TrackClassMode;
+    use crate::cost::{MahalanobisCost, WeightedCost};
+    use crate::kalman::{MotionModel, TrackState, CHI_SQUARE_95_4DOF};
+    use nalgebra::DMatrix;
+
+    /// Accepts every pair with similarity `value`, down to `min_similarity`.
+    struct ConstantCost {
+        value: f32,
+        min_similarity: f32,
+    }
+
+    impl AssociationCost<f32> for ConstantCost {
+        fn similarity(&self, input: &AssociationInput<'_, f32>) -> DMatrix<f32> {
+            DMatrix::from_element(
+                input.track_indices.len(),
+                input.detections.len(),
+                self.value,
+            )
+        }
+
+        fn min_similarity(&self) -> f32 {
+            self.min_similarity
+        }
+    }
+
+    const ANY_PAIR: ConstantCost = ConstantCost {
+        value: 0.0,
+        min_similarity: -5.0,
+    };
+
+    /// Runs a frame with a detection of class 0, then one with `second`, and
+    /// returns the output of the second frame.
+    fn second_frame(
+        mut tracker: ByteTrackTracker<f32>,
+        second: Detection<f32>,
+    ) -> Vec<TrackedDetection<f32>> {
+        tracker
+            .update(&[Detection::new([100.0, 100.0, 150.0, 200.0], 0, 0.9)])
+            .unwrap();
+        tracker.update(&[second]).unwrap()
+    }
+
+    #[test]
+    fn zero_width_detections_do_not_break_gated_tracking() {
+        let config = ByteTrackConfig::default()
+            .with_motion_model(MotionModel::Xywh)
+            .with_gating_threshold(CHI_SQUARE_95_4DOF);
+        let mut tracker = ByteTrackTracker::<f32>::new(config).unwrap();
+        let detections = [
+            Detection::new([100.0, 100.0, 100.0, 200.0], 0, 0.9),
+            Detection::new([300.0, 100.0, 350.0, 200.0], 0, 0.9),
+        ];
+
+        for _ in 0..5 {
+            tracker.update(&detections).unwrap();
+        }
+        assert!(tracker
+            .active_tracks()
+            .iter()
+            .any(|t| t.box_coords[0] == 300.0 && t.tracker_id >= 0));
+    }
+
+    #[test]
+    fn class_aware_cost_below_gated_similarity_keeps_classes_apart() {
+        let tracker = ByteTrackTracker::new(ByteTrackConfig::default().with_class_aware(true))
+            .unwrap()
+            .with_first_stage_cost(ANY_PAIR);
+        let far_other_class = Detection::new([400.0, 100.0, 450.0, 200.0], 1, 0.9);
+        assert_eq!(
+            second_frame(tracker, far_other_class)[0].predicted_box,
+            None
+        );
+
+        let tracker = ByteTrackTracker::new(ByteTrackConfig::default().with_class_aware(true))
+            .unwrap()
+            .with_first_stage_cost(ANY_PAIR);
+        let far_same_class = Detection::new([400.0, 100.0, 450.0, 200.0], 0, 0.9);
+        assert!(second_frame(tracker, far_same_class)[0]
+            .predicted_box
+            .is_some());
+    }
+
+    #[test]
+    fn class_aware_stages_never_match_other_classes() {
+        let bbox = [100.0, 100.0, 150.0, 200.0];
+        let config = ByteTrackConfig::default().with_class_aware(true);
+        for fuse_score in [false, true] {
+            let mut tracker = ByteTrackTracker::<f32>::new(config.clone())
+                .unwrap()
+                .with_fuse_score(fuse_score);
+            for _ in 0..2 {
+                tracker.update(&[Detection::new(bbox, 0, 0.9)]).unwrap();
+            }
+
+            // Same box, first stage then second stage score.
+            for score in [0.9, 0.3] {
+                let output = tracker.update(&[Detection::new(bbox, 1, score)]).unwrap();
+                assert!(output.iter().all(|d| d.tracker_id != 0), "{fuse_score}");
+                assert_eq!(tracker.lost_tracks()[0].tracker_id, 0);
+            }
+        }
+    }
+
+    #[test]
+    fn majority_vote_follows_the_detected_class() {
+        let config = ByteTrackConfig::default().with_track_class_mode(TrackClassMode::MajorityVote);
+        let mut tracker = ByteTrackTracker::<f32>::new(config).unwrap();
+        let bbox = [100.0, 100.0, 150.0, 200.0];
+        for (class_id, expected) in [(0, 0), (1, 0), (1, 1), (0, 1)] {
+            tracker
+                .update(&[Detection::new(bbox, class_id, 0.9)])
+                .unwrap();
+            let active = tracker.active_tracks();
+            assert_eq!(active.len(), 1);
+            assert_eq!(active[0].class_id, expected);
+        }
+    }
+
+    #[test]
+    fn weighted_cost_gates_below_its_own_minimum() {
+        let cost = WeightedCost::new(-100.0)
+            .with_term(0.0, MahalanobisCost::new(CHI_SQUARE_95_4DOF))
+            .with_term(1.0, ANY_PAIR);
+        let config = ByteTrackConfig::default().with_motion_model(MotionModel::Xywh);
+        let tracker = ByteTrackTracker::new(config)
+            .unwrap()
+            .with_first_stage_cost(cost);
+        let far = Detection::new([400.0, 100.0, 450.0, 200.0], 0, 0.9);
+        assert_eq!(second_frame(tracker, far)[0].predicted_box, None);
+    }
+
+    #[test]
+    fn invalid_custom_similarity_is_an_error() {
+        let mut tracker = ByteTrackTracker::new(ByteTrackConfig::default())
+            .unwrap()
+            .with_first_stage_cost(ConstantCost {
+                value: f32::NAN,
+                min_similarity: 0.0,
+            });
+        let detection = [Detection::new([100.0, 100.0, 150.0, 200.0], 0, 0.9)];
+        tracker.update(&detection).unwrap();
+        assert_eq!(
+            tracker.update(&detection).unwrap_err(),
+            TrackerError::InvalidCost { row: 0, col: 0 }
+        );
+    }
+
+    #[test]
+    fn failed_frame_leaves_the_tracker_unchanged() {
+        let mut tracker = ByteTrackTracker::new(ByteTrackConfig::default())
+            .unwrap()
+            .with_second_stage_cost(ConstantCost {
+                value: f32::NAN,
+                min_similarity: 0.0,
+            });
+        let a = [100.0, 100.0, 150.0, 200.0];
+        let b = [300.0, 100.0, 350.0, 200.0];
+        for _ in 0..3 {
+            tracker
+                .update(&[Detection::new(a, 0, 0.9), Detection::new(b, 0, 0.9)])
+                .unwrap();
+        }
+        let before = serde_json::to_string(&tracker.snapshot()).unwrap();
+
+        // `a` updates its track in the first stage, then the second stage
+        // fails on `b`.
+        let mut output = vec![TrackedDetection::new(
+            &Detection::new(a, 0, 0.9),
+            0,
+            0,
+            None,
+        )];
+        let failing = [Detection::new(a, 0, 0.9), Detection::new(b, 0, 0.3)];
+        assert_eq!(
+            tracker.update_into(&failing, &mut output).unwrap_err(),
+            TrackerError::InvalidCost { row: 0, col: 0 }
+        );
+        assert!(output.is_empty());
+        assert_eq!(serde_json::to_string(&tracker.snapshot()).unwrap(), before);
+
+        // Continuing gives the same state as a tracker restored from before.
+        let mut restored = ByteTrackTracker::restore(tracker.snapshot()).unwrap();
+        let next = [Detection::new(a, 0, 0.9)];
+        tracker.update(&next).unwrap();
+        restored.update(&next).unwrap();
+        assert_eq!(
+            serde_json::to_string(&tracker.snapshot()).unwrap(),
+            serde_json::to_string(&restored.snapshot()).unwrap()
+        );
+    }
+
+    #[test]
+    fn mahalanobis_gate_applies_to_custom_stage_costs() {
+        let gated_tracker = || {
+            let config = ByteTrackConfig::default()
+                .with_motion_model(MotionModel::Xywh)
+                .with_gating_threshold(CHI_SQUARE_95_4DOF);
+            ByteTrackTracker::new(config)
+                .unwrap()
+                .with_first_stage_cost(ANY_PAIR)
+        };
+
+        let far = Detection::new([400.0, 100.0, 450.0, 200.0], 0, 0.9);
+        assert_eq!(second_frame(gated_tracker(), far)[0].predicted_box, None);
+
+        let near = Detection::new([102.0, 101.0, 152.0, 201.0], 0, 0.9);
+        assert!(second_frame(gated_tracker(), near)[0]
+            .predicted_box
+            .is_some());
+    }
+
+    #[test]
+    fn appearance_gated_pairs_are_never_matched() {
+        let config = ByteTrackConfig::default().with_appearance(AppearanceConfig::default());
+        let mut tracker = ByteTrackTracker::<f32>::new(config).unwrap();
+        let bbox = [100.0, 100.0, 150.0, 200.0];
+        let detection = Detection::new(bbox, 0, 0.9).with_embedding(vec![1.0, 0.0]);
+        for _ in 0..2 {
+            tracker.update(std::slice::from_ref(&detection)).unwrap();
+        }
+
+        let stranger = Detection::new(bbox, 0, 0.9).with_embedding(vec![0.0, 1.0]);
+        assert_eq!(tracker.update(&[stranger]).unwrap()[0].tracker_id, -1);
+        assert_eq!(tracker.lost_tracks()[0].tracker_id, 0);
+    }
+
+    #[test]
+    fn lost_track_is_reactivated_with_its_id() {
+        let mut tracker = ByteTrackTracker::<f32>::new(ByteTrackConfig::default()).unwrap();
+        let detections = [Detection::new([100.0, 100.0, 150.0, 200.0], 0, 0.9)];
+        for _ in 0..3 {
+            tracker.update(&detections).unwrap();
+        }
+        assert_eq!(tracker.active_tracks()[0].tracker_id, 0);
+
+        tracker.update(&[]).unwrap();
+        let lost = tracker.lost_tracks();
+        assert_eq!(lost.len(), 1);
+        assert_eq!((lost[0].tracker_id, lost[0].state), (0, TrackState::Lost));
+        assert!(tracker.active_tracks().is_empty());
+
+        let output = tracker.update(&detections).unwrap();
+        assert_eq!(output[0].tracker_id, 0);
+        let active = tracker.active_tracks();
+        assert_eq!(active.len(), 1);
+        assert_eq!(
+            (active[0].tracker_id, active[0].state),
+            (0, TrackState::Confirmed)
+        );
+        assert!(tracker.lost_tracks().is_empty());
+    }
+
+    #[test]
+    fn unmatched_tentative_tracks_are_removed_in_order() {
+        let mut tracker = ByteTrackTracker::<f32>::new(ByteTrackConfig::default()).unwrap();
+        let kept = Detection::new([100.0, 100.0, 150.0, 200.0], 0, 0.9);
+        tracker
+            .update(&[
+                Detection::new([300.0, 100.0, 350.0, 200.0], 1, 0.9),
+                kept.clone(),
+                Detection::new([500.0, 100.0, 550.0, 200.0], 2, 0.9),
+            ])
+            .unwrap();
+
+        tracker.update(&[kept]).unwrap();
+        let removed = tracker.removed_tracks();
+        assert_eq!(removed.len(), 2);
+        for (track, class_id) in removed.iter().zip([1, 2]) {
+            assert_eq!(track.class_id, class_id);
+            assert_eq!(track.tracker_id, -1);
+            assert_eq!(track.state, TrackState::Removed);
+        }
+        assert_eq!(tracker.active_tracks().len(), 1);
+        assert_eq!(tracker.active_tracks()[0].class_id, 0);
+    }
+
+    #[test]
+    fn invalid_camera_motion_leaves_the_tracker_unchanged() {
+        let mut tracker = ByteTrackTracker::<f32>::new(ByteTrackConfig::default()).unwrap();
+        let detections = [Detection::new([100.0, 100.0, 150.0, 200.0], 0, 0.9)];
+        for _ in 0..3 {
+            tracker.update(&detections).unwrap();
+        }
+        let before = serde_json::to_string(&tracker.snapshot()).unwrap();
+
+        let nan = CameraMotion::Affine([[1.0, 0.0, f32::NAN], [0.0, 1.0, 0.0]]);
+        // w = 1 - x / 100 is negative at the track's center.
+        let behind =
+            CameraMotion::Homography([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [-0.01, 0.0, 1.0]]);
+        for motion in [nan, behind] {
+            assert_eq!(
+                tracker
+                    .update_with_camera_motion(&detections, Some(&motion))
+                    .unwrap_err(),
+                TrackerError::InvalidCameraMotion
+            );
+            assert_eq!(serde_json::to_string(&tracker.snapshot()).unwrap(), before);
+        }
+
+        let shift = CameraMotion::Affine([[1.0, 0.0, 2.0], [0.0, 1.0, 0.0]]);
+        tracker
+            .update_with_camera_motion(&detections, Some(&shift))
+            .unwrap();
+    }
+}
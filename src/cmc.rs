@@ -0,0 +1,156 @@
+use nalgebra::{Matrix2, Matrix3, Vector2, Vector3};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Result, TrackerError};
+use crate::float::Float;
+
+/// Per-frame global camera motion, mapping pixel coordinates of the previous
+/// frame to the current one. Estimated upstream, e.g. with OpenCV
+/// `estimateAffinePartial2D` or `findHomography`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CameraMotion<F: Float = f32> {
+    /// Row-major 2x3 affine matrix `[[a, b, tx], [c, d, ty]]`.
+    Affine([[F; 3]; 2]),
+    /// Row-major 3x3 homography.
+    Homography([[F; 3]; 3]),
+}
+
+impl<F: Float> CameraMotion<F> {
+    pub fn identity() -> Self {
+        let (zero, one) = (F::zero(), F::one());
+        Self::Affine([[one, zero, zero], [zero, one, zero]])
+    }
+
+    /// Checks that every entry of the matrix is finite.
+    pub fn validate(&self) -> Result<()> {
+        let finite = match self {
+            Self::Affine(m) => m.iter().flatten().all(|v| v.is_finite()),
+            Self::Homography(m) => m.iter().flatten().all(|v| v.is_finite()),
+        };
+        if finite {
+            Ok(())
+        } else {
+            Err(TrackerError::InvalidCameraMotion)
+        }
+    }
+
+    /// Affine approximation `x -> A x + t` of the motion around `point`.
+    ///
+    /// Affine motion is returned as is. A homography is linearized with its
+    /// Jacobian at `point`, which is exact for the point itself and close
+    /// for boxes that are small relative to the perspective change.
+    ///
+    /// Fails with [`TrackerError::InvalidCameraMotion`] if the result is not
+    /// finite, or if a homography maps `point` to `w <= 0`, where the
+    /// projection flips or diverges.
+    pub fn linearize(&self, point: &Vector2<F>) -> Result<(Matrix2<F>, Vector2<F>)> {
+        let (linear, translation) = match self {
+            Self::Affine(m) => (
+                Matrix2::new(m[0][0], m[0][1], m[1][0], m[1][1]),
+                Vector2::new(m[0][2], m[1][2]),
+            ),
+            Self::Homography(m) => {
+                let h = Matrix3::from_fn(|i, j| m[i][j]);
+                let p = h * Vector3::new(point.x, point.y, F::one());
+                let w = p.z;
+                if w <= F::zero() {
+                    return Err(TrackerError::InvalidCameraMotion);
+                }
+                let (u, v) = (p.x / w, p.y / w);
+
+                let jacobian = Matrix2::new(
+                    (h[(0, 0)] - u * h[(2, 0)]) / w,
+                    (h[(0, 1)] - u * h[(2, 1)]) / w,
+                    (h[(1, 0)] - v * h[(2, 0)]) / w,
+                    (h[(1, 1)] - v * h[(2, 1)]) / w,
+                );
+                let translation = Vector2::new(u, v) - jacobian * point;
+                (jacobian, translation)
+            }
+        };
+        if linear
+            .iter()
+            .chain(translation.iter())
+            .all(|v| v.is_finite())
+        {
+            Ok((linear, translation))
+        } else {
+            Err(TrackerError::InvalidCameraMotion)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PERSPECTIVE: [[f64; 3]; 3] = [[1.1, 0.05, 4.0], [-0.02, 0.95, -3.0], [1e-4, -2e-4, 1.0]];
+
+    fn project(m: &[[f64; 3]; 3], point: &Vector2<f64>) -> Vector2<f64> {
+        let p = Matrix3::from_fn(|i, j| m[i][j]) * Vector3::new(point.x, point.y, 1.0);
+        Vector2::new(p.x / p.z, p.y / p.z)
+    }
+
+    #[test]
+    fn homography_linearization_is_exact_at_the_point() {
+        let point = Vector2::new(320.0, 180.0);
+        let (jacobian, translation) = CameraMotion::Homography(PERSPECTIVE)
+            .linearize(&point)
+            .unwrap();
+        let expected = project(&PERSPECTIVE, &point);
+        assert!((jacobian * point + translation - expected).norm() < 1e-9);
+
+        // Columns of the Jacobian are the partial derivatives of the projection.
+        let eps = 1e-4;
+        for (axis, step) in [Vector2::new(eps, 0.0), Vector2::new(0.0, eps)]
+            .iter()
+            .enumerate()
+        {
+            let derivative = (project(&PERSPECTIVE, &(point + step))
+                - project(&PERSPECTIVE, &(point - step)))
+                / (2.0 * eps);
+            assert!((jacobian.column(axis) - derivative).norm() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn non_finite_motion_is_rejected() {
+        let point = Vector2::new(10.0, 10.0);
+        let mut affine = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+        affine[1][2] = f64::NAN;
+        assert_eq!(
+            CameraMotion::Affine(affine).validate(),
+            Err(TrackerError::InvalidCameraMotion)
+        );
+        assert!(CameraMotion::Affine(affine).linearize(&point).is_err());
+
+        let mut homography = PERSPECTIVE;
+        homography[0][0] = f64::INFINITY;
+        assert_eq!(
+            CameraMotion::Homography(homography).validate(),
+            Err(TrackerError::InvalidCameraMotion)
+        );
+        assert!(CameraMotion::<f64>::identity().validate().is_ok());
+    }
+
+    #[test]
+    fn homography_mapping_a_point_to_non_positive_w_is_rejected() {
+        // w = 1 - x / 100: zero on the line x = 100, negative beyond it.
+        let mut homography = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [-0.01, 0.0, 1.0]];
+        let motion = CameraMotion::Homography(homography);
+        assert!(motion.validate().is_ok());
+        assert!(motion.linearize(&Vector2::new(50.0, 0.0)).is_ok());
+        for x in [100.0, 150.0] {
+            assert_eq!(
+                motion.linearize(&Vector2::new(x, 0.0)),
+                Err(TrackerError::InvalidCameraMotion)
+            );
+        }
+
+        homography[2] = [0.0, 0.0, -1.0];
+        assert!(CameraMotion::Homography(homography)
+            .linearize(&Vector2::new(0.0, 0.0))
+            .is_err());
+    }
+}
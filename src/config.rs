@@ -0,0 +1,640 @@
+use serde::{Deserialize, Serialize};
+
+use crate::appearance::AppearanceConfig;
+use crate::assignment::AssignmentSolver;
+use crate::error::{ConfigError, Result};
+use crate::iou::IouMetric;
+use crate::kalman::{KalmanNoiseConfig, MotionModel};
+
+/// How a track decides its class in class-aware association.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrackClassMode {
+    /// Class of the detection that spawned the track.
+    #[default]
+    Fixed,
+    /// Most frequent class among all detections matched to the track.
+    MajorityVote,
+}
+
+/// Parameters of [`ByteTrackTracker`](crate::ByteTrackTracker).
+///
+/// Defaults match `ByteTrackTracker` from the Python `trackers` package.
+/// Deserializing fills missing fields with defaults, so a config file only
+/// needs the values it changes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ByteTrackConfig {
+    /// Number of frames a track is kept without updates, at 30 fps.
+    pub lost_track_buffer: i32,
+    /// Frame rate of the video, scales `lost_track_buffer`.
+    pub frame_rate: f64,
+    /// Minimum detection score to spawn a new track. May exceed
+    /// `high_conf_det_threshold`, as in the defaults: only the unmatched
+    /// high-confidence detections that also reach it spawn tracks.
+    pub track_activation_threshold: f64,
+    /// Number of successful updates before a track gets an ID.
+    pub minimum_consecutive_frames: i32,
+    /// Minimum IoU for a track and detection to be associated.
+    pub minimum_iou_threshold: f64,
+    /// Detection score separating first and second association stages.
+    pub high_conf_det_threshold: f64,
+    /// Only associate tracks and detections with the same `class_id`.
+    pub class_aware: bool,
+    /// How the class of a track is decided when `class_aware` is set.
+    pub track_class_mode: TrackClassMode,
+    /// Fuse embedding distance into the high-confidence association stage.
+    pub appearance: Option<AppearanceConfig>,
+    /// State representation and noise model of the Kalman filter.
+    pub motion_model: MotionModel,
+    pub noise: KalmanNoiseConfig,
+    /// Box similarity compared against `minimum_iou_threshold`. The
+    /// appearance stage keeps plain IoU.
+    pub iou_metric: IouMetric,
+    /// Forbid matches whose squared Mahalanobis distance from the track's
+    /// prediction exceeds this chi-square threshold, e.g.
+    /// [`CHI_SQUARE_95_4DOF`](crate::CHI_SQUARE_95_4DOF).
+    pub gating_threshold: Option<f64>,
+    /// Algorithm solving each association stage.
+    pub solver: AssignmentSolver,
+    /// Compare only track and detection boxes found to overlap through a
+    /// uniform grid instead of every pair, for scenes with hundreds of
+    /// objects. Matches stay the same. Applies to the `iou` and `buffered`
+    /// metrics without `class_aware`, `gating_threshold`, custom stage costs
+    /// or appearance.
+    pub spatial_index: bool,
+}
+
+impl Default for ByteTrackConfig {
+    fn default() -> Self {
+        Self {
+            lost_track_buffer: 30,
+            frame_rate: 30.0,
+            track_activation_threshold: 0.7,
+            minimum_consecutive_frames: 2,
+            minimum_iou_threshold: 0.1,
+            high_conf_det_threshold: 0.6,
+            class_aware: false,
+            track_class_mode: TrackClassMode::Fixed,
+            appearance: None,
+            motion_model: MotionModel::Xyxy,
+            noise: KalmanNoiseConfig::default(),
+            iou_metric: IouMetric::Iou,
+            gating_threshold: None,
+            solver: AssignmentSolver::Exact,
+            spatial_index: false,
+        }
+    }
+}
+
+impl ByteTrackConfig {
+    pub fn with_lost_track_buffer(mut self, lost_track_buffer: i32) -> Self {
+        self.lost_track_buffer = lost_track_buffer;
+        self
+    }
+
+    pub fn with_frame_rate(mut self, frame_rate: f64) -> Self {
+        self.frame_rate = frame_rate;
+        self
+    }
+
+    pub fn with_track_activation_threshold(mut self, track_activation_threshold: f64) -> Self {
+        self.track_activation_threshold = track_activation_threshold;
+        self
+    }
+
+    pub fn with_minimum_consecutive_frames(mut self, minimum_consecutive_frames: i32) -> Self {
+        self.minimum_consecutive_frames = minimum_consecutive_frames;
+        self
+    }
+
+    pub fn with_minimum_iou_threshold(mut self, minimum_iou_threshold: f64) -> Self {
+        self.minimum_iou_threshold = minimum_iou_threshold;
+        self
+    }
+
+    pub fn with_high_conf_det_threshold(mut self, high_conf_det_threshold: f64) -> Self {
+        self.high_conf_det_threshold = high_conf_det_threshold;
+        self
+    }
+
+    pub fn with_class_aware(mut self, class_aware: bool) -> Self {
+        self.class_aware = class_aware;
+        self
+    }
+
+    pub fn with_track_class_mode(mut self, track_class_mode: TrackClassMode) -> Self {
+        self.track_class_mode = track_class_mode;
+        self
+    }
+
+    pub fn with_appearance(mut self, appearance: AppearanceConfig) -> Self {
+        self.appearance = Some(appearance);
+        self
+    }
+
+    pub fn with_motion_model(mut self, motion_model: MotionModel) -> Self {
+        self.motion_model = motion_model;
+        self
+    }
+
+    pub fn with_noise(mut self, noise: KalmanNoiseConfig) -> Self {
+        self.noise = noise;
+        self
+    }
+
+    pub fn with_iou_metric(mut self, iou_metric: IouMetric) -> Self {
+        self.iou_metric = iou_metric;
+        self
+    }
+
+    pub fn with_gating_threshold(mut self, gating_threshold: f64) -> Self {
+        self.gating_threshold = Some(gating_threshold);
+        self
+    }
+
+    pub fn with_solver(mut self, solver: AssignmentSolver) -> Self {
+        self.solver = solver;
+        self
+    }
+
+    pub fn with_spatial_index(mut self, spatial_index: bool) -> Self {
+        self.spatial_index = spatial_index;
+        self
+    }
+
+    /// Number of frames without update after which a track is removed.
+    pub fn maximum_frames_without_update(&self) -> i32 {
+        maximum_frames_without_update(self.frame_rate, self.lost_track_buffer)
+    }
+
+    /// Checks every field on its own. `track_activation_threshold` is not
+    /// required to stay below `high_conf_det_threshold`: the Python defaults
+    /// (0.7 and 0.6) already break that order, and a higher activation
+    /// threshold only means fewer detections spawn tracks.
+    pub fn validate(&self) -> Result<()> {
+        check_track_lifetime(
+            self.lost_track_buffer,
+            self.frame_rate,
+            self.minimum_consecutive_frames,
+        )?;
+        check_unit_range(
+            "track_activation_threshold",
+            self.track_activation_threshold,
+        )?;
+        check_unit_range("minimum_iou_threshold", self.minimum_iou_threshold)?;
+        check_unit_range("high_conf_det_threshold", self.high_conf_det_threshold)?;
+        if let Some(appearance) = &self.appearance {
+            appearance.validate()?;
+        }
+        self.noise.validate()?;
+        self.iou_metric.validate()?;
+        check_gating_threshold(self.gating_threshold)?;
+
+        Ok(())
+    }
+}
+
+/// Parameters of [`BotSortTracker`](crate::BotSortTracker).
+///
+/// Defaults follow the BoT-SORT paper: new tracks need a 0.7 score, the
+/// first stage uses IoU fused with detection score and a 0.8 IoU distance
+/// threshold.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BotSortConfig {
+    /// Number of frames a track is kept without updates, at 30 fps.
+    pub lost_track_buffer: i32,
+    /// Frame rate of the video, scales `lost_track_buffer`.
+    pub frame_rate: f64,
+    /// Minimum detection score to spawn a new track.
+    pub track_activation_threshold: f64,
+    /// Number of successful updates before a track gets an ID.
+    pub minimum_consecutive_frames: i32,
+    /// Minimum (score-fused in the first stage) IoU for association.
+    pub minimum_iou_threshold: f64,
+    /// Detection score separating first and second association stages.
+    pub high_conf_det_threshold: f64,
+    /// Multiply IoU by detection score in the first association stage.
+    pub fuse_score: bool,
+    /// Only associate tracks and detections with the same `class_id`.
+    pub class_aware: bool,
+    /// Fuse embedding distance into the high-confidence association stage.
+    pub appearance: Option<AppearanceConfig>,
+    /// State representation and noise model of the Kalman filter.
+    pub motion_model: MotionModel,
+    pub noise: KalmanNoiseConfig,
+    /// Chi-square threshold on the squared Mahalanobis distance of a match.
+    pub gating_threshold: Option<f64>,
+    /// Algorithm solving each association stage.
+    pub solver: AssignmentSolver,
+    /// Compare only track and detection boxes found to overlap through a
+    /// uniform grid instead of every pair, see
+    /// [`ByteTrackConfig::spatial_index`]. Not used with `class_aware`.
+    pub spatial_index: bool,
+}
+
+impl Default for BotSortConfig {
+    fn default() -> Self {
+        Self {
+            lost_track_buffer: 30,
+            frame_rate: 30.0,
+            track_activation_threshold: 0.7,
+            minimum_consecutive_frames: 2,
+            minimum_iou_threshold: 0.2,
+            high_conf_det_threshold: 0.6,
+            fuse_score: true,
+            class_aware: false,
+            appearance: None,
+            motion_model: MotionModel::Xywh,
+            noise: KalmanNoiseConfig::default(),
+            gating_threshold: None,
+            solver: AssignmentSolver::Exact,
+            spatial_index: false,
+        }
+    }
+}
+
+impl BotSortConfig {
+    pub fn with_lost_track_buffer(mut self, lost_track_buffer: i32) -> Self {
+        self.lost_track_buffer = lost_track_buffer;
+        self
+    }
+
+    pub fn with_frame_rate(mut self, frame_rate: f64) -> Self {
+        self.frame_rate = frame_rate;
+        self
+    }
+
+    pub fn with_track_activation_threshold(mut self, track_activation_threshold: f64) -> Self {
+        self.track_activation_threshold = track_activation_threshold;
+        self
+    }
+
+    pub fn with_minimum_consecutive_frames(mut self, minimum_consecutive_frames: i32) -> Self {
+        self.minimum_consecutive_frames = minimum_consecutive_frames;
+        self
+    }
+
+    pub fn with_minimum_iou_threshold(mut self, minimum_iou_threshold: f64) -> Self {
+        self.minimum_iou_threshold = minimum_iou_threshold;
+        self
+    }
+
+    pub fn with_high_conf_det_threshold(mut self, high_conf_det_threshold: f64) -> Self {
+        self.high_conf_det_threshold = high_conf_det_threshold;
+        self
+    }
+
+    pub fn with_fuse_score(mut self, fuse_score: bool) -> Self {
+        self.fuse_score = fuse_score;
+        self
+    }
+
+    pub fn with_class_aware(mut self, class_aware: bool) -> Self {
+        self.class_aware = class_aware;
+        self
+    }
+
+    pub fn with_appearance(mut self, appearance: AppearanceConfig) -> Self {
+        self.appearance = Some(appearance);
+        self
+    }
+
+    pub fn with_motion_model(mut self, motion_model: MotionModel) -> Self {
+        self.motion_model = motion_model;
+        self
+    }
+
+    pub fn with_noise(mut self, noise: KalmanNoiseConfig) -> Self {
+        self.noise = noise;
+        self
+    }
+
+    pub fn with_gating_threshold(mut self, gating_threshold: f64) -> Self {
+        self.gating_threshold = Some(gating_threshold);
+        self
+    }
+
+    pub fn with_solver(mut self, solver: AssignmentSolver) -> Self {
+        self.solver = solver;
+        self
+    }
+
+    pub fn with_spatial_index(mut self, spatial_index: bool) -> Self {
+        self.spatial_index = spatial_index;
+        self
+    }
+
+    /// ByteTrack parameters BoT-SORT shares, validated by the caller.
+    pub(crate) fn to_bytetrack(&self) -> ByteTrackConfig {
+        ByteTrackConfig {
+            lost_track_buffer: self.lost_track_buffer,
+            frame_rate: self.frame_rate,
+            track_activation_threshold: self.track_activation_threshold,
+            minimum_consecutive_frames: self.minimum_consecutive_frames,
+            minimum_iou_threshold: self.minimum_iou_threshold,
+            high_conf_det_threshold: self.high_conf_det_threshold,
+            class_aware: self.class_aware,
+            track_class_mode: TrackClassMode::Fixed,
+            appearance: self.appearance.clone(),
+            motion_model: self.motion_model,
+            noise: self.noise,
+            iou_metric: IouMetric::Iou,
+            gating_threshold: self.gating_threshold,
+            solver: self.solver,
+            spatial_index: self.spatial_index,
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        self.to_bytetrack().validate()
+    }
+}
+
+/// Parameters of [`SortTracker`](crate::SortTracker).
+///
+/// Defaults match `SORTTracker` from the Python `trackers` package.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SortConfig {
+    /// Number of frames a track is kept without updates, at 30 fps.
+    pub lost_track_buffer: i32,
+    /// Frame rate of the video, scales `lost_track_buffer`.
+    pub frame_rate: f64,
+    /// Minimum detection score to spawn a new track.
+    pub track_activation_threshold: f64,
+    /// Number of successful updates before a track gets an ID.
+    pub minimum_consecutive_frames: i32,
+    /// Minimum IoU for a track and detection to be associated.
+    pub minimum_iou_threshold: f64,
+    /// State representation and noise model of the Kalman filter.
+    pub motion_model: MotionModel,
+    pub noise: KalmanNoiseConfig,
+    /// Algorithm solving the association.
+    pub solver: AssignmentSolver,
+    /// Compute IoU only for boxes found to overlap through a uniform grid
+    /// instead of every pair, for scenes with hundreds of objects. Matches
+    /// stay the same.
+    pub spatial_index: bool,
+}
+
+impl Default for SortConfig {
+    fn default() -> Self {
+        Self {
+            lost_track_buffer: 30,
+            frame_rate: 30.0,
+            track_activation_threshold: 0.25,
+            minimum_consecutive_frames: 3,
+            minimum_iou_threshold: 0.3,
+            motion_model: MotionModel::Xyxy,
+            noise: KalmanNoiseConfig::default(),
+            solver: AssignmentSolver::Exact,
+            spatial_index: false,
+        }
+    }
+}
+
+impl SortConfig {
+    pub fn with_lost_track_buffer(mut self, lost_track_buffer: i32) -> Self {
+        self.lost_track_buffer = lost_track_buffer;
+        self
+    }
+
+    pub fn with_frame_rate(mut self, frame_rate: f64) -> Self {
+        self.frame_rate = frame_rate;
+        self
+    }
+
+    pub fn with_track_activation_threshold(mut self, track_activation_threshold: f64) -> Self {
+        self.track_activation_threshold = track_activation_threshold;
+        self
+    }
+
+    pub fn with_minimum_consecutive_frames(mut self, minimum_consecutive_frames: i32) -> Self {
+        self.minimum_consecutive_frames = minimum_consecutive_frames;
+        self
+    }
+
+    pub fn with_minimum_iou_threshold(mut self, minimum_iou_threshold: f64) -> Self {
+        self.minimum_iou_threshold = minimum_iou_threshold;
+        self
+    }
+
+    pub fn with_motion_model(mut self, motion_model: MotionModel) -> Self {
+        self.motion_model = motion_model;
+        self
+    }
+
+    pub fn with_noise(mut self, noise: KalmanNoiseConfig) -> Self {
+        self.noise = noise;
+        self
+    }
+
+    pub fn with_solver(mut self, solver: AssignmentSolver) -> Self {
+        self.solver = solver;
+        self
+    }
+
+    pub fn with_spatial_index(mut self, spatial_index: bool) -> Self {
+        self.spatial_index = spatial_index;
+        self
+    }
+
+    /// Number of frames without update after which a track is removed.
+    pub fn maximum_frames_without_update(&self) -> i32 {
+        maximum_frames_without_update(self.frame_rate, self.lost_track_buffer)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        check_track_lifetime(
+            self.lost_track_buffer,
+            self.frame_rate,
+            self.minimum_consecutive_frames,
+        )?;
+        check_unit_range(
+            "track_activation_threshold",
+            self.track_activation_threshold,
+        )?;
+        check_unit_range("minimum_iou_threshold", self.minimum_iou_threshold)?;
+        self.noise.validate()?;
+
+        Ok(())
+    }
+}
+
+/// Parameters of [`OcSortTracker`](crate::OcSortTracker).
+///
+/// Defaults follow the OC-SORT paper's MOT17 setup. Association always uses
+/// [`AssignmentSolver::Exact`]: its cost mixes IoU with motion direction
+/// consistency, which has no cost limit for the other solvers to prune by.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OcSortConfig {
+    /// Number of frames a track is kept without updates, at 30 fps.
+    pub lost_track_buffer: i32,
+    /// Frame rate of the video, scales `lost_track_buffer`.
+    pub frame_rate: f64,
+    /// Detections below this score are neither associated nor spawn tracks.
+    pub high_conf_det_threshold: f64,
+    /// Number of successful updates before a track gets an ID.
+    pub minimum_consecutive_frames: i32,
+    /// Minimum IoU for a track and detection to be associated.
+    pub minimum_iou_threshold: f64,
+    /// Weight of the velocity direction consistency term in the cost matrix.
+    pub direction_consistency_weight: f64,
+    /// Frame gap between observations used to estimate track direction.
+    pub delta_t: i32,
+    /// State representation and noise model of the Kalman filter.
+    pub motion_model: MotionModel,
+    pub noise: KalmanNoiseConfig,
+}
+
+impl Default for OcSortConfig {
+    fn default() -> Self {
+        Self {
+            lost_track_buffer: 30,
+            frame_rate: 30.0,
+            high_conf_det_threshold: 0.6,
+            minimum_consecutive_frames: 3,
+            minimum_iou_threshold: 0.3,
+            direction_consistency_weight: 0.2,
+            delta_t: 3,
+            motion_model: MotionModel::Xyxy,
+            noise: KalmanNoiseConfig::default(),
+        }
+    }
+}
+
+impl OcSortConfig {
+    pub fn with_lost_track_buffer(mut self, lost_track_buffer: i32) -> Self {
+        self.lost_track_buffer = lost_track_buffer;
+        self
+    }
+
+    pub fn with_frame_rate(mut self, frame_rate: f64) -> Self {
+        self.frame_rate = frame_rate;
+        self
+    }
+
+    pub fn with_high_conf_det_threshold(mut self, high_conf_det_threshold: f64) -> Self {
+        self.high_conf_det_threshold = high_conf_det_threshold;
+        self
+    }
+
+    pub fn with_minimum_consecutive_frames(mut self, minimum_consecutive_frames: i32) -> Self {
+        self.minimum_consecutive_frames = minimum_consecutive_frames;
+        self
+    }
+
+    pub fn with_minimum_iou_threshold(mut self, minimum_iou_threshold: f64) -> Self {
+        self.minimum_iou_threshold = minimum_iou_threshold;
+        self
+    }
+
+    pub fn with_direction_consistency_weight(mut self, direction_consistency_weight: f64) -> Self {
+        self.direction_consistency_weight = direction_consistency_weight;
+        self
+    }
+
+    pub fn with_delta_t(mut self, delta_t: i32) -> Self {
+        self.delta_t = delta_t;
+        self
+    }
+
+    pub fn with_motion_model(mut self, motion_model: MotionModel) -> Self {
+        self.motion_model = motion_model;
+        self
+    }
+
+    pub fn with_noise(mut self, noise: KalmanNoiseConfig) -> Self {
+        self.noise = noise;
+        self
+    }
+
+    /// Number of frames without update after which a track is removed.
+    pub fn maximum_frames_without_update(&self) -> i32 {
+        maximum_frames_without_update(self.frame_rate, self.lost_track_buffer)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        check_track_lifetime(
+            self.lost_track_buffer,
+            self.frame_rate,
+            self.minimum_consecutive_frames,
+        )?;
+        check_unit_range("high_conf_det_threshold", self.high_conf_det_threshold)?;
+        check_unit_range("minimum_iou_threshold", self.minimum_iou_threshold)?;
+        if !(self.direction_consistency_weight.is_finite()
+            && self.direction_consistency_weight >= 0.0)
+        {
+            return Err(ConfigError::NegativeWeight {
+                name: "direction_consistency_weight",
+                value: self.direction_consistency_weight,
+            }
+            .into());
+        }
+        if self.delta_t < 1 {
+            return Err(ConfigError::InvalidDeltaT(self.delta_t).into());
+        }
+        self.noise.validate()?;
+
+        Ok(())
+    }
+}
+
+fn maximum_frames_without_update(frame_rate: f64, lost_track_buffer: i32) -> i32 {
+    (frame_rate / 30.0 * lost_track_buffer as f64) as i32
+}
+
+fn check_track_lifetime(
+    lost_track_buffer: i32,
+    frame_rate: f64,
+    minimum_consecutive_frames: i32,
+) -> Result<()> {
+    if lost_track_buffer < 0 {
+        return Err(ConfigError::NegativeLostTrackBuffer(lost_track_buffer).into());
+    }
+    if !(frame_rate.is_finite() && frame_rate > 0.0) {
+        return Err(ConfigError::InvalidFrameRate(frame_rate).into());
+    }
+    if minimum_consecutive_frames < 1 {
+        return Err(
+            ConfigError::InvalidMinimumConsecutiveFrames(minimum_consecutive_frames).into(),
+        );
+    }
+    Ok(())
+}
+
+fn check_gating_threshold(gating_threshold: Option<f64>) -> Result<()> {
+    match gating_threshold {
+        Some(value) if !(value.is_finite() && value > 0.0) => {
+            Err(ConfigError::InvalidGatingThreshold(value).into())
+        }
+        _ => Ok(()),
+    }
+}
+
+pub(crate) fn check_unit_range(name: &'static str, value: f64) -> Result<()> {
+    if !(0.0..=1.0).contains(&value) {
+        return Err(ConfigError::ThresholdOutOfRange { name, value }.into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn activation_threshold_may_exceed_high_conf_threshold() {
+        let defaults = ByteTrackConfig::default();
+        assert!(defaults.track_activation_threshold > defaults.high_conf_det_threshold);
+        assert!(defaults.validate().is_ok());
+
+        let config = ByteTrackConfig::default()
+            .with_track_activation_threshold(0.95)
+            .with_high_conf_det_threshold(0.1);
+        assert!(config.validate().is_ok());
+    }
+}
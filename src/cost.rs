@@ -0,0 +1,217 @@
+use nalgebra::DMatrix;
+
+use crate::detection::Detection;
+use crate::float::Float;
+use crate::iou::IouMetric;
+use crate::kalman::KalmanBoxTracker;
+
+/// Tracks and detections taking part in one association stage.
+pub struct AssociationInput<'a, F: Float = f64> {
+    /// All tracks of the tracker, predicted to the current frame.
+    pub tracks: &'a [KalmanBoxTracker<F>],
+    /// Predicted box of every track in `tracks`.
+    pub predicted_boxes: &'a [[F; 4]],
+    /// Indices into `tracks` of the stage's tracks, one matrix row each.
+    pub track_indices: &'a [usize],
+    /// Detections of the stage, one matrix column each.
+    pub detections: &'a [Detection<F>],
+}
+
+impl<F: Float> AssociationInput<'_, F> {
+    /// Track of matrix row `row`.
+    pub fn track(&self, row: usize) -> &KalmanBoxTracker<F> {
+        &self.tracks[self.track_indices[row]]
+    }
+
+    /// Predicted box of the track of matrix row `row`.
+    pub fn predicted_box(&self, row: usize) -> &[F; 4] {
+        &self.predicted_boxes[self.track_indices[row]]
+    }
+}
+
+/// Scores how well tracks and detections fit together in an association
+/// stage, see [`ByteTrackTracker::with_first_stage_cost`](crate::ByteTrackTracker::with_first_stage_cost).
+pub trait AssociationCost<F: Float = f64>: Send + Sync {
+    /// Similarity of every track (rows) to every detection (columns), higher
+    /// is better. `-inf` gates a pair; NaN and `+inf` fail the update with
+    /// [`TrackerError::InvalidCost`](crate::TrackerError::InvalidCost).
+    fn similarity(&self, input: &AssociationInput<'_, F>) -> DMatrix<F>;
+
+    /// Pairs below this similarity are never matched.
+    fn min_similarity(&self) -> F;
+}
+
+/// Overlap of the predicted track box and the detection box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IouCost {
+    pub metric: IouMetric,
+    pub min_similarity: f64,
+}
+
+impl IouCost {
+    pub fn new(metric: IouMetric, min_similarity: f64) -> Self {
+        Self {
+            metric,
+            min_similarity,
+        }
+    }
+}
+
+impl<F: Float> AssociationCost<F> for IouCost {
+    fn similarity(&self, input: &AssociationInput<'_, F>) -> DMatrix<F> {
+        DMatrix::from_fn(input.track_indices.len(), input.detections.len(), |i, j| {
+            self.metric
+                .similarity(input.predicted_box(i), &input.detections[j].box_coords)
+        })
+    }
+
+    fn min_similarity(&self) -> F {
+        F::of_f64(self.min_similarity)
+    }
+}
+
+/// `1 - d / max_distance` for the distance `d` in pixels between the centers
+/// of the predicted track box and the detection box, negative beyond
+/// `max_distance`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CenterDistanceCost {
+    pub max_distance: f64,
+}
+
+impl CenterDistanceCost {
+    pub fn new(max_distance: f64) -> Self {
+        Self { max_distance }
+    }
+}
+
+impl<F: Float> AssociationCost<F> for CenterDistanceCost {
+    fn similarity(&self, input: &AssociationInput<'_, F>) -> DMatrix<F> {
+        let max_distance = F::of_f64(self.max_distance);
+        DMatrix::from_fn(input.track_indices.len(), input.detections.len(), |i, j| {
+            let a = input.predicted_box(i);
+            let b = &input.detections[j].box_coords;
+            let two = F::of_f64(2.0);
+            let dx = (b[0] + b[2] - a[0] - a[2]) / two;
+            let dy = (b[1] + b[3] - a[1] - a[3]) / two;
+            F::one() - (dx * dx + dy * dy).sqrt() / max_distance
+        })
+    }
+
+    fn min_similarity(&self) -> F {
+        F::zero()
+    }
+}
+
+/// `1 - d` for the cosine distance `d` between the detection embedding and
+/// the track's feature gallery. Pairs without features on both sides or
+/// farther than `max_cosine_distance` are gated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EmbeddingCost {
+    pub max_cosine_distance: f64,
+}
+
+impl EmbeddingCost {
+    pub fn new(max_cosine_distance: f64) -> Self {
+        Self {
+            max_cosine_distance,
+        }
+    }
+}
+
+impl<F: Float> AssociationCost<F> for EmbeddingCost {
+    fn similarity(&self, input: &AssociationInput<'_, F>) -> DMatrix<F> {
+        let max_cosine_distance = F::of_f64(self.max_cosine_distance);
+        DMatrix::from_fn(input.track_indices.len(), input.detections.len(), |i, j| {
+            let distance = input.detections[j]
+                .embedding
+                .as_deref()
+                .and_then(|embedding| input.track(i).gallery().distance(embedding));
+            match distance {
+                Some(distance) if distance <= max_cosine_distance => F::one() - distance,
+                _ => F::of_f64(f64::NEG_INFINITY),
+            }
+        })
+    }
+
+    fn min_similarity(&self) -> F {
+        F::one() - F::of_f64(self.max_cosine_distance)
+    }
+}
+
+/// Negated squared Mahalanobis distance of the detection box from the
+/// track's Kalman prediction, see [`KalmanBoxTracker::mahalanobis_distance`].
+/// Pairs beyond the chi-square `gating_threshold` are gated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MahalanobisCost {
+    pub gating_threshold: f64,
+}
+
+impl MahalanobisCost {
+    pub fn new(gating_threshold: f64) -> Self {
+        Self { gating_threshold }
+    }
+}
+
+impl<F: Float> AssociationCost<F> for MahalanobisCost {
+    fn similarity(&self, input: &AssociationInput<'_, F>) -> DMatrix<F> {
+        DMatrix::from_fn(input.track_indices.len(), input.detections.len(), |i, j| {
+            // A singular covariance gates the pair instead of failing the frame.
+            input
+                .track(i)
+                .mahalanobis_distance(&input.detections[j].box_coords)
+                .map_or(F::of_f64(f64::NEG_INFINITY), |distance| -distance)
+        })
+    }
+
+    fn min_similarity(&self) -> F {
+        -F::of_f64(self.gating_threshold)
+    }
+}
+
+/// Weighted sum of several costs. A pair gated by any of them is gated.
+pub struct WeightedCost<F: Float = f64> {
+    terms: Vec<(f64, Box<dyn AssociationCost<F>>)>,
+    min_similarity: f64,
+}
+
+impl<F: Float> WeightedCost<F> {
+    /// Creates an empty sum matching pairs whose weighted similarity reaches
+    /// `min_similarity`.
+    pub fn new(min_similarity: f64) -> Self {
+        Self {
+            terms: Vec::new(),
+            min_similarity,
+        }
+    }
+
+    pub fn with_term(mut self, weight: f64, cost: impl AssociationCost<F> + 'static) -> Self {
+        self.terms.push((weight, Box::new(cost)));
+        self
+    }
+}
+
+impl<F: Float> AssociationCost<F> for WeightedCost<F> {
+    fn similarity(&self, input: &AssociationInput<'_, F>) -> DMatrix<F> {
+        let mut total = DMatrix::zeros(input.track_indices.len(), input.detections.len());
+        let mut gated = DMatrix::from_element(total.nrows(), total.ncols(), false);
+        for (weight, cost) in &self.terms {
+            let similarity = cost.similarity(input);
+            let min_similarity = cost.min_similarity();
+            for (k, &value) in similarity.iter().enumerate() {
+                gated[k] |= value < min_similarity;
+                total[k] += F::of_f64(*weight) * value;
+            }
+        }
+
+        total.zip_apply(&gated, |value, gated| {
+            if gated {
+                *value = F::of_f64(f64::NEG_INFINITY);
+            }
+        });
+        total
+    }
+
+    fn min_similarity(&self) -> F {
+        F::of_f64(self.min_similarity)
+    }
+}
@@ -1,22 +1,88 @@
 use serde::{Deserialize, Serialize};
 
+use crate::error::{Result, TrackerError};
+use crate::float::Float;
+use crate::kalman::{has_finite_measurement, MotionModel};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Detection {
+pub struct Detection<F: Float = f32> {
     #[serde(rename = "box")]
-    pub box_coords: [f32; 4],
+    pub box_coords: [F; 4],
     pub class_id: i32,
-    pub score: f32,
+    pub score: F,
+    /// Optional re-identification feature used for appearance association.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<Vec<F>>,
+}
+
+impl<F: Float> Detection<F> {
+    pub fn new(box_coords: [F; 4], class_id: i32, score: F) -> Self {
+        Self {
+            box_coords,
+            class_id,
+            score,
+            embedding: None,
+        }
+    }
+
+    pub fn with_embedding(mut self, embedding: Vec<F>) -> Self {
+        self.embedding = Some(embedding);
+        self
+    }
+}
+
+/// Rejects detections with a non-finite score or a box without a finite
+/// measurement in `motion_model`.
+pub(crate) fn check_detections<F: Float>(
+    detections: &[Detection<F>],
+    motion_model: MotionModel,
+) -> Result<()> {
+    match detections
+        .iter()
+        .position(|d| !(d.score.is_finite() && has_finite_measurement(&d.box_coords, motion_model)))
+    {
+        Some(index) => Err(TrackerError::InvalidDetection { index }),
+        None => Ok(()),
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TrackedDetection {
+pub struct TrackedDetection<F: Float = f32> {
     #[serde(rename = "box")]
-    pub box_coords: [f32; 4],
+    pub box_coords: [F; 4],
     pub tracker_id: i32,
+    #[serde(default)]
+    pub class_id: i32,
+    #[serde(default)]
+    pub score: F,
+    /// Index of the source detection in the slice passed to `update`.
+    #[serde(default)]
+    pub detection_index: usize,
+    /// Kalman-predicted box of the matched track, `None` for unmatched detections.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub predicted_box: Option<[F; 4]>,
+}
+
+impl<F: Float> TrackedDetection<F> {
+    pub fn new(
+        detection: &Detection<F>,
+        detection_index: usize,
+        tracker_id: i32,
+        predicted_box: Option<[F; 4]>,
+    ) -> Self {
+        Self {
+            box_coords: detection.box_coords,
+            tracker_id,
+            class_id: detection.class_id,
+            score: detection.score,
+            detection_index,
+            predicted_box,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct InputData {
+pub struct InputData<F: Float = f32> {
     #[serde(default)]
     pub video_path: Option<String>,
     #[serde(default)]
@@ -24,15 +90,15 @@ pub struct InputData {
     #[serde(default)]
     pub threshold: Option<f32>,
     pub total_frames: usize,
-    pub detections: Vec<Vec<Detection>>,
+    pub detections: Vec<Vec<Detection<F>>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct OutputData {
+pub struct OutputData<F: Float = f32> {
     pub source_file: String,
     pub tracker: String,
     pub total_frames: usize,
     #[serde(default)]
     pub avg_performance_ms: Option<f64>,
-    pub detections: Vec<Vec<TrackedDetection>>,
+    pub detections: Vec<Vec<TrackedDetection<F>>>,
 }
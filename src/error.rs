@@ -0,0 +1,155 @@
+use std::fmt;
+
+pub type Result<T> = std::result::Result<T, TrackerError>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrackerError {
+    /// Flat cost matrix length does not match `n_rows * n_cols`.
+    CostMatrixShape {
+        len: usize,
+        n_rows: usize,
+        n_cols: usize,
+    },
+    /// Cost matrix entry at `row`, `col` is NaN or negative infinity.
+    InvalidCost { row: usize, col: usize },
+    /// Infinite costs leave no complete assignment, e.g. a row whose costs
+    /// are all `+inf`.
+    InfeasibleCostMatrix,
+    /// Tracker configuration failed validation.
+    Config(ConfigError),
+    /// Frame at `index` of a batch belongs to a stream that already has a
+    /// frame earlier in the batch.
+    DuplicateStream { index: usize },
+    /// Detection at `index` has a NaN or infinite score, or a box without a
+    /// finite Kalman measurement (non-finite coordinates, or zero height with
+    /// the XYAH motion model).
+    InvalidDetection { index: usize },
+    /// Box without a finite Kalman measurement, e.g. zero height with the
+    /// XYAH motion model.
+    NonFiniteMeasurement([f64; 4]),
+    /// Innovation covariance of a Kalman update is not positive definite.
+    SingularCovariance,
+    /// Camera motion with a non-finite entry, or a homography mapping a
+    /// track's center to or behind the line at infinity (`w <= 0`).
+    InvalidCameraMotion,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    NegativeLostTrackBuffer(i32),
+    InvalidFrameRate(f64),
+    InvalidMinimumConsecutiveFrames(i32),
+    /// Threshold outside of `0.0..=1.0`.
+    ThresholdOutOfRange {
+        name: &'static str,
+        value: f64,
+    },
+    /// Cost weight that is negative or not finite.
+    NegativeWeight {
+        name: &'static str,
+        value: f64,
+    },
+    InvalidDeltaT(i32),
+    /// Feature gallery history must hold at least one embedding.
+    EmptyGalleryBudget,
+    /// Kalman noise parameter that is zero, negative or not finite.
+    NonPositiveNoise {
+        name: &'static str,
+        value: f64,
+    },
+    /// Chi-square gating threshold that is zero, negative or not finite.
+    InvalidGatingThreshold(f64),
+}
+
+impl fmt::Display for TrackerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CostMatrixShape {
+                len,
+                n_rows,
+                n_cols,
+            } => write!(
+                f,
+                "cost matrix has {} elements, expected {}x{}",
+                len, n_rows, n_cols
+            ),
+            Self::InvalidCost { row, col } => {
+                write!(f, "cost matrix entry ({}, {}) is NaN or -inf", row, col)
+            }
+            Self::InfeasibleCostMatrix => write!(f, "cost matrix is infeasible"),
+            Self::Config(err) => write!(f, "invalid tracker config: {}", err),
+            Self::DuplicateStream { index } => {
+                write!(f, "frame {} repeats a stream of the same batch", index)
+            }
+            Self::InvalidDetection { index } => {
+                write!(f, "detection {} has a non-finite score or box", index)
+            }
+            Self::NonFiniteMeasurement(bbox) => {
+                write!(f, "box {:?} has no finite Kalman measurement", bbox)
+            }
+            Self::SingularCovariance => {
+                write!(f, "Kalman innovation covariance is not positive definite")
+            }
+            Self::InvalidCameraMotion => {
+                write!(f, "camera motion is not finite or maps a track to w <= 0")
+            }
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NegativeLostTrackBuffer(value) => {
+                write!(f, "lost_track_buffer must not be negative, got {}", value)
+            }
+            Self::InvalidFrameRate(value) => {
+                write!(f, "frame_rate must be positive, got {}", value)
+            }
+            Self::InvalidMinimumConsecutiveFrames(value) => {
+                write!(
+                    f,
+                    "minimum_consecutive_frames must be at least 1, got {}",
+                    value
+                )
+            }
+            Self::ThresholdOutOfRange { name, value } => {
+                write!(f, "{} must be within 0..=1, got {}", name, value)
+            }
+            Self::NegativeWeight { name, value } => {
+                write!(f, "{} must be a non-negative number, got {}", name, value)
+            }
+            Self::InvalidDeltaT(value) => {
+                write!(f, "delta_t must be at least 1, got {}", value)
+            }
+            Self::EmptyGalleryBudget => write!(f, "gallery budget must be at least 1"),
+            Self::NonPositiveNoise { name, value } => {
+                write!(f, "{} must be a positive number, got {}", name, value)
+            }
+            Self::InvalidGatingThreshold(value) => {
+                write!(
+                    f,
+                    "gating_threshold must be a positive number, got {}",
+                    value
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for TrackerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Config(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<ConfigError> for TrackerError {
+    fn from(err: ConfigError) -> Self {
+        Self::Config(err)
+    }
+}
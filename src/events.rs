@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+
+use crate::float::Float;
+use crate::kalman::KalmanBoxTracker;
+
+/// Lifecycle transition of a track.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrackEventKind {
+    /// A detection spawned a new, tentative track.
+    Created,
+    /// The track got its ID.
+    Confirmed,
+    /// A confirmed track missed its first frame.
+    Lost,
+    /// A lost track was matched again and kept its ID.
+    Refound,
+    /// The track was dropped.
+    Removed,
+}
+
+/// Lifecycle event emitted by [`Tracker::update`](crate::Tracker::update).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrackEvent<F: Float = f32> {
+    pub kind: TrackEventKind,
+    /// Zero-based index of the update call that produced the event.
+    pub frame_index: u64,
+    /// `-1` for tracks that were never confirmed.
+    pub tracker_id: i32,
+    pub class_id: i32,
+    /// Last observed box as `[x1, y1, x2, y2]`.
+    pub box_coords: [F; 4],
+}
+
+impl<F: Float> TrackEvent<F> {
+    fn new(kind: TrackEventKind, frame_index: u64, track: &KalmanBoxTracker<F>) -> Self {
+        Self {
+            kind,
+            frame_index,
+            tracker_id: track.tracker_id,
+            class_id: track.class_id,
+            box_coords: track.last_observation(),
+        }
+    }
+}
+
+/// Appends the transitions of `tracks` since the previous frame and a
+/// `Removed` event per track in `removed`.
+pub(crate) fn record_events<'a, F: Float>(
+    frame_index: u64,
+    tracks: impl IntoIterator<Item = &'a mut KalmanBoxTracker<F>>,
+    removed: &[KalmanBoxTracker<F>],
+    events: &mut Vec<TrackEvent<F>>,
+) {
+    for track in tracks {
+        if let Some(kind) = track.take_transition() {
+            events.push(TrackEvent::new(kind, frame_index, track));
+        }
+    }
+    for track in removed {
+        events.push(TrackEvent::new(TrackEventKind::Removed, frame_index, track));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bytetrack::ByteTrackTracker;
+    use crate::config::ByteTrackConfig;
+    use crate::detection::Detection;
+    use crate::tracker::Tracker;
+
+    #[test]
+    fn events_follow_the_track_lifecycle_in_order() {
+        use TrackEventKind::*;
+
+        let config = ByteTrackConfig::default().with_lost_track_buffer(2);
+        let mut tracker = ByteTrackTracker::<f32>::new(config).unwrap();
+        let a = Detection::new([100.0, 100.0, 150.0, 200.0], 0, 0.9);
+        let b = Detection::new([300.0, 100.0, 350.0, 200.0], 1, 0.9);
+        let c = Detection::new([500.0, 100.0, 550.0, 200.0], 2, 0.9);
+        let frames = [
+            vec![a.clone(), b],
+            vec![a.clone()],
+            vec![],
+            vec![a],
+            vec![],
+            vec![c],
+        ];
+        let expected: [&[(TrackEventKind, i32, i32)]; 6] = [
+            &[(Created, -1, 0), (Created, -1, 1)],
+            // The tentative track of `b` is dropped without an ID.
+            &[(Confirmed, 0, 0), (Removed, -1, 1)],
+            &[(Lost, 0, 0)],
+            &[(Refound, 0, 0)],
+            &[(Lost, 0, 0)],
+            // Transitions of kept tracks come before removals.
+            &[(Created, -1, 2), (Removed, 0, 0)],
+        ];
+
+        for (frame_index, (detections, expected)) in frames.iter().zip(expected).enumerate() {
+            tracker.update(detections).unwrap();
+            let events: Vec<_> = tracker
+                .events()
+                .iter()
+                .map(|event| {
+                    assert_eq!(event.frame_index, frame_index as u64);
+                    (event.kind, event.tracker_id, event.class_id)
+                })
+                .collect();
+            assert_eq!(events, expected, "frame {frame_index}");
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use std::fmt::{Debug, Display};
+use std::iter::Sum;
+
+use nalgebra::RealField;
+
+use crate::simd::IouKernel;
+
+/// Floating point type of boxes, scores and filter state: `f32` for speed,
+/// or `f64` to compute like the float64 Python reference. Implemented for
+/// these two types only.
+pub trait Float:
+    RealField + Copy + Default + Debug + Display + Sum + Send + Sync + IouKernel
+{
+    fn of_f32(value: f32) -> Self;
+    fn of_f64(value: f64) -> Self;
+    fn as_f64(self) -> f64;
+}
+
+impl Float for f32 {
+    fn of_f32(value: f32) -> Self {
+        value
+    }
+
+    fn of_f64(value: f64) -> Self {
+        value as f32
+    }
+
+    fn as_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl Float for f64 {
+    fn of_f32(value: f32) -> Self {
+        value as f64
+    }
+
+    fn of_f64(value: f64) -> Self {
+        value
+    }
+
+    fn as_f64(self) -> f64 {
+        self
+    }
+}
@@ -1,13 +1,106 @@
 use nalgebra::DMatrix;
+use serde::{Deserialize, Serialize};
 
-pub fn compute_iou(box1: &[f32; 4], box2: &[f32; 4]) -> f32 {
+use crate::error::{ConfigError, Result};
+use crate::float::Float;
+use crate::simd::iou_batch_by_column;
+use crate::spatial::{compute_iou_sparse, SparseSimilarity};
+
+/// Box similarity used to associate tracks with detections.
+///
+/// GIoU, DIoU and CIoU are rescaled to `0..=1` as `(x + 1) / 2`, clamped
+/// for CIoU which reaches `-1.5`, so they stay comparable to
+/// `minimum_iou_threshold`. Unlike
+/// IoU they keep decreasing with the distance between disjoint boxes, which
+/// lets small fast-moving objects match without overlap.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IouMetric {
+    #[default]
+    Iou,
+    /// Generalized IoU, penalized by the empty area of the enclosing box.
+    Giou,
+    /// Distance IoU, penalized by the squared distance between centers.
+    Diou,
+    /// Complete IoU, DIoU further penalized by the aspect ratio mismatch.
+    Ciou,
+    /// IoU of both boxes grown by `scale` times their width and height on
+    /// each side, as in C-BIoU.
+    Buffered { scale: f64 },
+}
+
+impl IouMetric {
+    pub fn similarity<F: Float>(&self, box1: &[F; 4], box2: &[F; 4]) -> F {
+        let rescale = |x: F| ((x + F::one()) / F::of_f64(2.0)).clamp(F::zero(), F::one());
+        match *self {
+            Self::Iou => compute_iou(box1, box2),
+            Self::Giou => rescale(compute_giou(box1, box2)),
+            Self::Diou => rescale(compute_diou(box1, box2)),
+            Self::Ciou => rescale(compute_ciou(box1, box2)),
+            Self::Buffered { scale } => compute_buffered_iou(box1, box2, F::of_f64(scale)),
+        }
+    }
+
+    /// Similarity of every predicted box (rows) to every detection box
+    /// (columns), like [`compute_iou_batch`].
+    pub fn similarity_batch<F: Float>(
+        &self,
+        predicted_boxes: &[[F; 4]],
+        detection_boxes: &[[F; 4]],
+    ) -> DMatrix<F> {
+        if *self == Self::Iou {
+            return compute_iou_batch(predicted_boxes, detection_boxes);
+        }
+        DMatrix::from_fn(predicted_boxes.len(), detection_boxes.len(), |i, j| {
+            self.similarity(&predicted_boxes[i], &detection_boxes[j])
+        })
+    }
+
+    /// Like [`similarity_batch`](Self::similarity_batch), evaluating only
+    /// pairs found to overlap through a spatial index. `None` for GIoU, DIoU
+    /// and CIoU, which are not zero for disjoint boxes.
+    pub fn similarity_sparse<F: Float>(
+        &self,
+        predicted_boxes: &[[F; 4]],
+        detection_boxes: &[[F; 4]],
+    ) -> Option<SparseSimilarity<F>> {
+        match *self {
+            Self::Iou => Some(compute_iou_sparse(predicted_boxes, detection_boxes)),
+            Self::Buffered { scale } => {
+                let scale = F::of_f64(scale);
+                Some(SparseSimilarity::from_overlaps(
+                    predicted_boxes,
+                    detection_boxes,
+                    |bbox| buffered(bbox, scale),
+                    |box1, box2| compute_buffered_iou(box1, box2, scale),
+                ))
+            }
+            Self::Giou | Self::Diou | Self::Ciou => None,
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if let Self::Buffered { scale } = *self {
+            if !(scale.is_finite() && scale >= 0.0) {
+                return Err(ConfigError::NegativeWeight {
+                    name: "iou_metric.scale",
+                    value: scale,
+                }
+                .into());
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn compute_iou<F: Float>(box1: &[F; 4], box2: &[F; 4]) -> F {
     let x1_inter = box1[0].max(box2[0]);
     let y1_inter = box1[1].max(box2[1]);
     let x2_inter = box1[2].min(box2[2]);
     let y2_inter = box1[3].min(box2[3]);
 
     if x2_inter <= x1_inter || y2_inter <= y1_inter {
-        return 0.0;
+        return F::zero();
     }
 
     let inter_area = (x2_inter - x1_inter) * (y2_inter - y1_inter);
@@ -15,31 +108,209 @@ pub fn compute_iou(box1: &[f32; 4], box2: &[f32; 4]) -> f32 {
     let box2_area = (box2[2] - box2[0]) * (box2[3] - box2[1]);
     let union_area = box1_area + box2_area - inter_area;
 
-    if union_area <= 0.0 {
-        return 0.0;
+    if union_area <= F::zero() {
+        return F::zero();
     }
 
     inter_area / union_area
 }
 
-pub fn compute_iou_batch(
-    predicted_boxes: &[[f32; 4]],
-    detection_boxes: &[[f32; 4]],
-) -> DMatrix<f32> {
-    let n_trackers = predicted_boxes.len();
-    let n_detections = detection_boxes.len();
+/// IoU of every predicted box (rows) with every detection box (columns).
+///
+/// Uses AVX when available, comparing one detection with several predicted
+/// boxes per instruction; results equal [`compute_iou`] on each pair.
+pub fn compute_iou_batch<F: Float>(
+    predicted_boxes: &[[F; 4]],
+    detection_boxes: &[[F; 4]],
+) -> DMatrix<F> {
+    iou_batch_by_column(predicted_boxes, detection_boxes)
+}
 
-    if n_trackers == 0 || n_detections == 0 {
-        return DMatrix::zeros(n_trackers, n_detections);
+/// Generalized IoU in `-1..=1`: IoU minus the share of the smallest
+/// enclosing box not covered by either box.
+pub fn compute_giou<F: Float>(box1: &[F; 4], box2: &[F; 4]) -> F {
+    let iou = compute_iou(box1, box2);
+    let enclosing = enclosing_box(box1, box2);
+    let enclosing_area = (enclosing[2] - enclosing[0]) * (enclosing[3] - enclosing[1]);
+    if enclosing_area <= F::zero() {
+        return iou;
     }
 
-    let mut iou_matrix = DMatrix::zeros(n_trackers, n_detections);
+    let union_area = area(box1) + area(box2) - intersection_area(box1, box2);
+    iou - (enclosing_area - union_area) / enclosing_area
+}
+
+/// Distance IoU in `-1..=1`: IoU minus the squared distance between the box
+/// centers over the squared diagonal of the smallest enclosing box.
+pub fn compute_diou<F: Float>(box1: &[F; 4], box2: &[F; 4]) -> F {
+    compute_iou(box1, box2) - center_distance_penalty(box1, box2)
+}
 
-    for (i, pred_box) in predicted_boxes.iter().enumerate() {
-        for (j, det_box) in detection_boxes.iter().enumerate() {
-            iou_matrix[(i, j)] = compute_iou(pred_box, det_box);
+/// Complete IoU in `-1.5..=1`: DIoU minus a term for the difference of the
+/// aspect ratios, weighted more as the overlap grows.
+pub fn compute_ciou<F: Float>(box1: &[F; 4], box2: &[F; 4]) -> F {
+    let iou = compute_iou(box1, box2);
+    // `atan2` keeps zero-height boxes finite.
+    let angle1 = (box1[2] - box1[0]).atan2(box1[3] - box1[1]);
+    let angle2 = (box2[2] - box2[0]).atan2(box2[3] - box2[1]);
+    let v = F::of_f64(4.0) / (F::pi() * F::pi()) * (angle2 - angle1) * (angle2 - angle1);
+    let denominator = F::one() - iou + v;
+    let alpha = if denominator > F::zero() {
+        v / denominator
+    } else {
+        F::zero()
+    };
+
+    iou - center_distance_penalty(box1, box2) - alpha * v
+}
+
+/// IoU of `box1` and `box2` after growing each by `scale` times its width
+/// and height on every side.
+pub fn compute_buffered_iou<F: Float>(box1: &[F; 4], box2: &[F; 4], scale: F) -> F {
+    compute_iou(&buffered(box1, scale), &buffered(box2, scale))
+}
+
+fn buffered<F: Float>(bbox: &[F; 4], scale: F) -> [F; 4] {
+    let dx = (bbox[2] - bbox[0]) * scale;
+    let dy = (bbox[3] - bbox[1]) * scale;
+    [bbox[0] - dx, bbox[1] - dy, bbox[2] + dx, bbox[3] + dy]
+}
+
+fn area<F: Float>(bbox: &[F; 4]) -> F {
+    (bbox[2] - bbox[0]) * (bbox[3] - bbox[1])
+}
+
+fn intersection_area<F: Float>(box1: &[F; 4], box2: &[F; 4]) -> F {
+    let w = box1[2].min(box2[2]) - box1[0].max(box2[0]);
+    let h = box1[3].min(box2[3]) - box1[1].max(box2[1]);
+    w.max(F::zero()) * h.max(F::zero())
+}
+
+fn enclosing_box<F: Float>(box1: &[F; 4], box2: &[F; 4]) -> [F; 4] {
+    [
+        box1[0].min(box2[0]),
+        box1[1].min(box2[1]),
+        box1[2].max(box2[2]),
+        box1[3].max(box2[3]),
+    ]
+}
+
+/// Squared center distance over the squared enclosing diagonal, zero when
+/// both boxes collapse to the same point.
+fn center_distance_penalty<F: Float>(box1: &[F; 4], box2: &[F; 4]) -> F {
+    let dx = (box2[0] + box2[2] - box1[0] - box1[2]) / F::of_f64(2.0);
+    let dy = (box2[1] + box2[3] - box1[1] - box1[3]) / F::of_f64(2.0);
+    let enclosing = enclosing_box(box1, box2);
+    let cw = enclosing[2] - enclosing[0];
+    let ch = enclosing[3] - enclosing[1];
+    let diagonal = cw * cw + ch * ch;
+    if diagonal <= F::zero() {
+        return F::zero();
+    }
+
+    (dx * dx + dy * dy) / diagonal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const METRICS: [IouMetric; 5] = [
+        IouMetric::Iou,
+        IouMetric::Giou,
+        IouMetric::Diou,
+        IouMetric::Ciou,
+        IouMetric::Buffered { scale: 0.5 },
+    ];
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-12, "{actual} != {expected}");
+    }
+
+    /// Checks `[IoU, GIoU, DIoU, CIoU, buffered IoU]` of both box orders.
+    fn assert_metrics(box1: [f64; 4], box2: [f64; 4], expected: [f64; 5]) {
+        for (a, b) in [(&box1, &box2), (&box2, &box1)] {
+            let actual = [
+                compute_iou(a, b),
+                compute_giou(a, b),
+                compute_diou(a, b),
+                compute_ciou(a, b),
+                compute_buffered_iou(a, b, 0.5),
+            ];
+            for (actual, expected) in actual.into_iter().zip(expected) {
+                assert_close(actual, expected);
+            }
         }
     }
 
-    iou_matrix
+    #[test]
+    fn identical_boxes() {
+        assert_metrics([0.0, 0.0, 10.0, 10.0], [0.0, 0.0, 10.0, 10.0], [1.0; 5]);
+    }
+
+    #[test]
+    fn contained_box() {
+        assert_metrics(
+            [0.0, 0.0, 10.0, 10.0],
+            [2.0, 2.0, 6.0, 6.0],
+            [0.16, 0.16, 0.15, 0.15, 0.16],
+        );
+    }
+
+    #[test]
+    fn disjoint_boxes() {
+        assert_metrics(
+            [0.0, 0.0, 10.0, 10.0],
+            [20.0, 0.0, 30.0, 10.0],
+            [0.0, -1.0 / 3.0, -0.4, -0.4, 0.0],
+        );
+        assert_close(
+            compute_buffered_iou(&[0.0, 0.0, 10.0, 10.0], &[20.0, 0.0, 30.0, 10.0], 1.0),
+            0.2,
+        );
+    }
+
+    #[test]
+    fn zero_area_boxes() {
+        assert_metrics(
+            [5.0, 5.0, 5.0, 5.0],
+            [0.0, 0.0, 10.0, 10.0],
+            [0.0, 0.0, 0.0, -0.05, 0.0],
+        );
+        assert_metrics([5.0, 5.0, 5.0, 5.0], [5.0, 5.0, 5.0, 5.0], [0.0; 5]);
+        assert_metrics(
+            [0.0, 5.0, 10.0, 5.0],
+            [0.0, 5.0, 10.0, 5.0],
+            [0.0, 0.0, 0.0, 0.0, 0.0],
+        );
+    }
+
+    #[test]
+    fn similarity_stays_within_unit_range() {
+        let boxes: [[f64; 4]; 8] = [
+            [0.0, 0.0, 10.0, 10.0],
+            [2.0, 2.0, 6.0, 6.0],
+            [20.0, 0.0, 30.0, 10.0],
+            [5.0, 5.0, 5.0, 5.0],
+            [0.0, 5.0, 10.0, 5.0],
+            // Far apart with opposite aspect ratios, where CIoU reaches -1.5.
+            [0.0, 0.0, 1000.0, 1.0],
+            [5000.0, 0.0, 5001.0, 1000.0],
+            [-3.0, 4.0, 8.0, 9.0],
+        ];
+        for metric in METRICS {
+            for box1 in &boxes {
+                for box2 in &boxes {
+                    let similarity = metric.similarity(box1, box2);
+                    assert!(
+                        (0.0..=1.0).contains(&similarity),
+                        "{metric:?} of {box1:?} and {box2:?} is {similarity}"
+                    );
+                }
+            }
+        }
+        let ciou = compute_ciou(&boxes[5], &boxes[6]);
+        assert!((-1.5..-1.0).contains(&ciou));
+        assert_eq!(IouMetric::Ciou.similarity(&boxes[5], &boxes[6]), 0.0);
+    }
 }
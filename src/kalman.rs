@@ -1,73 +1,978 @@
-use nalgebra::{DVector, OMatrix, OVector, U4, U8};
+use nalgebra::{OMatrix, OVector, Vector2, U4, U8};
+use serde::{Deserialize, Serialize};
+
+use crate::appearance::{FeatureGallery, GalleryMode};
+use crate::cmc::CameraMotion;
+use crate::config::TrackClassMode;
+use crate::error::{ConfigError, Result, TrackerError};
+use crate::events::TrackEventKind;
+use crate::float::Float;
+
+/// Fixed standard deviations of the aspect ratio in the XYAH model.
+const STD_ASPECT: f64 = 1e-2;
+const STD_ASPECT_VELOCITY: f64 = 1e-5;
+const STD_ASPECT_MEASUREMENT: f64 = 1e-1;
+
+/// Smallest box size, in pixels, that noise scales with. Keeps the
+/// covariance of zero-width or zero-height boxes positive definite.
+const MIN_NOISE_SIZE: f64 = 1.0;
+
+/// Smallest factor confidence-adaptive noise scales `R` by, reached at a
+/// score of 0.99. Keeps `R` positive for detections scored 1.0.
+const MIN_CONFIDENCE_NOISE_SCALE: f64 = 1e-2;
+
+/// 0.95 quantile of the chi-square distribution with 4 degrees of freedom,
+/// the usual gate on the squared Mahalanobis distance of a box measurement.
+pub const CHI_SQUARE_95_4DOF: f64 = 9.4877;
+
+/// Quantities tracked by the 8-dimensional constant velocity Kalman state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MotionModel {
+    /// Raw `[x1, y1, x2, y2]` with fixed noise, as in the Python `trackers`.
+    #[default]
+    Xyxy,
+    /// Center, width and height with noise proportional to box size, as in BoT-SORT.
+    Xywh,
+    /// Center, aspect ratio `w / h` and height with noise proportional to
+    /// height, as in ByteTrack and DeepSORT.
+    Xyah,
+}
+
+/// Lifecycle of a track.
+///
+/// New tracks are `Tentative` until they get an ID. A `Confirmed` track that
+/// misses a frame becomes `Lost`, keeps its ID and returns to `Confirmed`
+/// when matched again. Tracks dropped by the tracker end up `Removed`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrackState {
+    Tentative,
+    Confirmed,
+    Lost,
+    Removed,
+}
+
+/// Process and measurement noise of the Kalman filter.
+///
+/// The XYXY model uses the fixed variances, the XYWH and XYAH models the
+/// standard deviation weights relative to box size.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KalmanNoiseConfig {
+    /// Process noise variance of every state entry (XYXY).
+    pub process_noise: f64,
+    /// Measurement noise variance of every box coordinate (XYXY).
+    pub measurement_noise: f64,
+    /// Position standard deviation relative to box size (XYWH, XYAH).
+    pub std_weight_position: f64,
+    /// Velocity standard deviation relative to box size (XYWH, XYAH).
+    pub std_weight_velocity: f64,
+    /// Scale measurement noise by `1 - score` (NSA Kalman from StrongSORT),
+    /// so low-confidence detections pull the state less. The factor is at
+    /// least 0.01, so that a score of 1.0 does not zero the noise.
+    pub confidence_adaptive: bool,
+}
+
+impl Default for KalmanNoiseConfig {
+    fn default() -> Self {
+        Self {
+            process_noise: 0.01,
+            measurement_noise: 0.1,
+            std_weight_position: 1.0 / 20.0,
+            std_weight_velocity: 1.0 / 160.0,
+            confidence_adaptive: false,
+        }
+    }
+}
+
+impl KalmanNoiseConfig {
+    pub fn with_process_noise(mut self, process_noise: f64) -> Self {
+        self.process_noise = process_noise;
+        self
+    }
+
+    pub fn with_measurement_noise(mut self, measurement_noise: f64) -> Self {
+        self.measurement_noise = measurement_noise;
+        self
+    }
+
+    pub fn with_std_weight_position(mut self, std_weight_position: f64) -> Self {
+        self.std_weight_position = std_weight_position;
+        self
+    }
+
+    pub fn with_std_weight_velocity(mut self, std_weight_velocity: f64) -> Self {
+        self.std_weight_velocity = std_weight_velocity;
+        self
+    }
+
+    pub fn with_confidence_adaptive(mut self, confidence_adaptive: bool) -> Self {
+        self.confidence_adaptive = confidence_adaptive;
+        self
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        for (name, value) in [
+            ("process_noise", self.process_noise),
+            ("measurement_noise", self.measurement_noise),
+            ("std_weight_position", self.std_weight_position),
+            ("std_weight_velocity", self.std_weight_velocity),
+        ] {
+            if !(value > 0.0 && value.is_finite()) {
+                return Err(ConfigError::NonPositiveNoise { name, value }.into());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Serializable copy of a [`KalmanBoxTracker`]. The transition and
+/// measurement matrices are constant and rebuilt on restore.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct TrackSnapshot<F: Float> {
+    tracker_id: i32,
+    time_since_update: i32,
+    number_of_successful_updates: i32,
+    class_id: i32,
+    track_state: TrackState,
+    reported_state: Option<TrackState>,
+    last_observation: [F; 4],
+    class_votes: Vec<(i32, u32)>,
+    gallery: FeatureGallery<F>,
+    motion_model: MotionModel,
+    noise: KalmanNoiseConfig,
+    state: [F; 8],
+    /// Column-major, as stored by nalgebra.
+    covariance: [[F; 8]; 8],
+}
 
 #[derive(Clone)]
-pub struct KalmanBoxTracker {
+pub struct KalmanBoxTracker<F: Float = f64> {
     pub tracker_id: i32,
     pub time_since_update: i32,
     pub number_of_successful_updates: i32,
-    state: OVector<f32, U8>,
-    f: OMatrix<f32, U8, U8>,
-    h: OMatrix<f32, U4, U8>,
-    q: OMatrix<f32, U8, U8>,
-    r: OMatrix<f32, U4, U4>,
-    p: OMatrix<f32, U8, U8>,
+    pub class_id: i32,
+    track_state: TrackState,
+    /// State when events were last recorded, `None` for a new track.
+    reported_state: Option<TrackState>,
+    last_observation: [F; 4],
+    /// Detections per class, empty until the first vote so that creating a
+    /// track does not allocate.
+    class_votes: Vec<(i32, u32)>,
+    gallery: FeatureGallery<F>,
+    motion_model: MotionModel,
+    noise: KalmanNoiseConfig,
+    state: OVector<F, U8>,
+    f: OMatrix<F, U8, U8>,
+    h: OMatrix<F, U4, U8>,
+    p: OMatrix<F, U8, U8>,
 }
 
-impl KalmanBoxTracker {
-    pub fn new(bbox: &[f32; 4]) -> Self {
-        let mut state = OVector::<f32, U8>::zeros();
-        state[0] = bbox[0];
-        state[1] = bbox[1];
-        state[2] = bbox[2];
-        state[3] = bbox[3];
+impl<F: Float> KalmanBoxTracker<F> {
+    pub fn new(bbox: &[F; 4], class_id: i32) -> Self {
+        Self::with_motion_model(bbox, class_id, MotionModel::Xyxy)
+    }
+
+    pub fn with_motion_model(bbox: &[F; 4], class_id: i32, motion_model: MotionModel) -> Self {
+        Self::with_noise(bbox, class_id, motion_model, KalmanNoiseConfig::default())
+    }
 
-        let mut f = OMatrix::<f32, U8, U8>::identity();
-        for i in 0..4 {
-            f[(i, i + 4)] = 1.0;
-        }
+    pub fn with_noise(
+        bbox: &[F; 4],
+        class_id: i32,
+        motion_model: MotionModel,
+        noise: KalmanNoiseConfig,
+    ) -> Self {
+        let measurement = to_measurement(bbox, motion_model);
+        let mut state = OVector::<F, U8>::zeros();
+        state.fixed_rows_mut::<4>(0).copy_from(&measurement);
 
-        let h = OMatrix::<f32, U4, U8>::identity();
-        let q = OMatrix::<f32, U8, U8>::identity() * 0.01;
-        let r = OMatrix::<f32, U4, U4>::identity() * 0.1;
-        let p = OMatrix::<f32, U8, U8>::identity();
+        let position = F::of_f64(2.0 * noise.std_weight_position);
+        let velocity = F::of_f64(10.0 * noise.std_weight_velocity);
+        let p = match motion_model {
+            MotionModel::Xyxy => OMatrix::<F, U8, U8>::identity(),
+            MotionModel::Xywh => {
+                size_scaled_covariance(measurement[2], measurement[3], position, velocity)
+            }
+            MotionModel::Xyah => height_scaled_covariance(measurement[3], position, velocity),
+        };
 
         Self {
             tracker_id: -1,
             time_since_update: 0,
             number_of_successful_updates: 1,
+            class_id,
+            track_state: TrackState::Tentative,
+            reported_state: None,
+            last_observation: *bbox,
+            class_votes: Vec::new(),
+            gallery: FeatureGallery::default(),
+            motion_model,
+            noise,
             state,
-            f,
-            h,
-            q,
-            r,
+            f: transition_matrix(),
+            h: OMatrix::<F, U4, U8>::identity(),
             p,
         }
     }
 
+    /// Copy of the complete track state for [`ByteTrackSnapshot`](crate::ByteTrackSnapshot).
+    pub(crate) fn snapshot(&self) -> TrackSnapshot<F> {
+        TrackSnapshot {
+            tracker_id: self.tracker_id,
+            time_since_update: self.time_since_update,
+            number_of_successful_updates: self.number_of_successful_updates,
+            class_id: self.class_id,
+            track_state: self.track_state,
+            reported_state: self.reported_state,
+            last_observation: self.last_observation,
+            class_votes: self.class_votes.clone(),
+            gallery: self.gallery.clone(),
+            motion_model: self.motion_model,
+            noise: self.noise,
+            state: self.state.into(),
+            covariance: self.p.into(),
+        }
+    }
+
+    pub(crate) fn from_snapshot(snapshot: TrackSnapshot<F>) -> Self {
+        Self {
+            tracker_id: snapshot.tracker_id,
+            time_since_update: snapshot.time_since_update,
+            number_of_successful_updates: snapshot.number_of_successful_updates,
+            class_id: snapshot.class_id,
+            track_state: snapshot.track_state,
+            reported_state: snapshot.reported_state,
+            last_observation: snapshot.last_observation,
+            class_votes: snapshot.class_votes,
+            gallery: snapshot.gallery,
+            motion_model: snapshot.motion_model,
+            noise: snapshot.noise,
+            state: snapshot.state.into(),
+            f: transition_matrix(),
+            h: OMatrix::<F, U4, U8>::identity(),
+            p: snapshot.covariance.into(),
+        }
+    }
+
+    pub fn motion_model(&self) -> MotionModel {
+        self.motion_model
+    }
+
+    pub fn track_state(&self) -> TrackState {
+        self.track_state
+    }
+
+    /// Gives the track its ID.
+    pub(crate) fn confirm(&mut self, tracker_id: i32) {
+        self.tracker_id = tracker_id;
+        self.track_state = TrackState::Confirmed;
+    }
+
+    /// Called on confirmed tracks without a match in the latest frame.
+    pub(crate) fn mark_lost(&mut self) {
+        if self.track_state == TrackState::Confirmed {
+            self.track_state = TrackState::Lost;
+        }
+    }
+
+    pub(crate) fn mark_removed(&mut self) {
+        self.track_state = TrackState::Removed;
+    }
+
+    /// Lifecycle change since the previous call, made once per frame.
+    pub(crate) fn take_transition(&mut self) -> Option<TrackEventKind> {
+        match (
+            self.reported_state.replace(self.track_state),
+            self.track_state,
+        ) {
+            (None, _) => Some(TrackEventKind::Created),
+            (Some(TrackState::Tentative), TrackState::Confirmed) => Some(TrackEventKind::Confirmed),
+            (Some(TrackState::Confirmed), TrackState::Lost) => Some(TrackEventKind::Lost),
+            (Some(TrackState::Lost), TrackState::Confirmed) => Some(TrackEventKind::Refound),
+            _ => None,
+        }
+    }
+
+    /// Replaces the filter by a copy saved earlier, keeping what was already
+    /// reported as events.
+    pub(crate) fn roll_back(&mut self, saved: KalmanBoxTracker<F>) {
+        let reported_state = self.reported_state;
+        *self = saved;
+        self.reported_state = reported_state;
+    }
+
+    /// Box of the latest matched detection.
+    pub fn last_observation(&self) -> [F; 4] {
+        self.last_observation
+    }
+
     pub fn predict(&mut self) {
+        let position = F::of_f64(self.noise.std_weight_position);
+        let velocity = F::of_f64(self.noise.std_weight_velocity);
+        let q = match self.motion_model {
+            MotionModel::Xyxy => {
+                OMatrix::<F, U8, U8>::identity() * F::of_f64(self.noise.process_noise)
+            }
+            MotionModel::Xywh => {
+                size_scaled_covariance(self.state[2], self.state[3], position, velocity)
+            }
+            MotionModel::Xyah => height_scaled_covariance(self.state[3], position, velocity),
+        };
+
         self.state = self.f * self.state;
-        self.p = self.f * self.p * self.f.transpose() + self.q;
+        self.p = self.f * self.p * self.f.transpose() + q;
         self.time_since_update += 1;
     }
 
-    pub fn update(&mut self, bbox: &[f32; 4]) {
+    /// Corrects the state with a matched box. On error the track is left
+    /// unchanged.
+    pub fn update(&mut self, bbox: &[F; 4]) -> Result<()> {
+        self.update_with_score(bbox, None)
+    }
+
+    /// Same as [`update`](Self::update), scaling measurement noise by
+    /// `1 - score`, at least 0.01, when the filter is confidence-adaptive.
+    pub fn update_with_score(&mut self, bbox: &[F; 4], score: Option<F>) -> Result<()> {
+        self.correct(bbox, score)?;
+
         self.time_since_update = 0;
         self.number_of_successful_updates += 1;
+        // Re-found tracks keep their ID.
+        if self.track_state == TrackState::Lost {
+            self.track_state = TrackState::Confirmed;
+        }
+        self.last_observation = *bbox;
 
-        let measurement = DVector::<f32>::from_row_slice(bbox);
+        Ok(())
+    }
 
-        let s = self.h * self.p * self.h.transpose() + self.r;
-        let s_inv = s.try_inverse().expect("Failed to invert S matrix");
-        let k = self.p * self.h.transpose() * s_inv;
+    /// Corrects only the filter state, for virtual boxes that must not count
+    /// as matched detections (OC-SORT's re-update). On error the track is
+    /// left unchanged.
+    pub(crate) fn correct(&mut self, bbox: &[F; 4], score: Option<F>) -> Result<()> {
+        let measurement = to_measurement(bbox, self.motion_model);
+        if !measurement.iter().all(|v| v.is_finite()) {
+            return Err(TrackerError::NonFiniteMeasurement(bbox.map(F::as_f64)));
+        }
+
+        let r = self.measurement_noise(score);
+
+        // K = P H^T S^-1, solved through the Cholesky factor of S instead of
+        // an explicit inverse, using the symmetry of P and S.
+        let s = self.h * self.p * self.h.transpose() + r;
+        let cholesky = s.cholesky().ok_or(TrackerError::SingularCovariance)?;
+        let k = cholesky.solve(&(self.h * self.p)).transpose();
 
         let y = measurement - (self.h * self.state);
         self.state += k * y;
 
-        let i = OMatrix::<f32, U8, U8>::identity();
-        self.p = (i - k * self.h) * self.p;
+        // Joseph form keeps P symmetric positive semi-definite under rounding.
+        let i_kh = OMatrix::<F, U8, U8>::identity() - k * self.h;
+        self.p = i_kh * self.p * i_kh.transpose() + k * r * k.transpose();
+
+        Ok(())
+    }
+
+    /// Covariance `S = H P H^T + R` of the difference between a measurement
+    /// and the predicted state, in the measurement space of the motion model.
+    /// `R` is not scaled by detection score.
+    pub fn innovation_covariance(&self) -> OMatrix<F, U4, U4> {
+        self.h * self.p * self.h.transpose() + self.measurement_noise(None)
+    }
+
+    /// Squared Mahalanobis distance of `bbox` from the predicted state under
+    /// [`innovation_covariance`](Self::innovation_covariance). Compare it
+    /// with a chi-square quantile such as [`CHI_SQUARE_95_4DOF`].
+    pub fn mahalanobis_distance(&self, bbox: &[F; 4]) -> Result<F> {
+        let measurement = to_measurement(bbox, self.motion_model);
+        if !measurement.iter().all(|v| v.is_finite()) {
+            return Err(TrackerError::NonFiniteMeasurement(bbox.map(F::as_f64)));
+        }
+
+        let cholesky = self
+            .innovation_covariance()
+            .cholesky()
+            .ok_or(TrackerError::SingularCovariance)?;
+        let y = measurement - (self.h * self.state);
+        Ok(y.dot(&cholesky.solve(&y)))
+    }
+
+    fn measurement_noise(&self, score: Option<F>) -> OMatrix<F, U4, U4> {
+        let pos = F::of_f64(self.noise.std_weight_position);
+        let mut r = match self.motion_model {
+            MotionModel::Xyxy => {
+                OMatrix::<F, U4, U4>::identity() * F::of_f64(self.noise.measurement_noise)
+            }
+            MotionModel::Xywh => {
+                let (w, h) = (noise_size(self.state[2]), noise_size(self.state[3]));
+                diagonal_covariance([pos * w, pos * h, pos * w, pos * h])
+            }
+            MotionModel::Xyah => {
+                let h = noise_size(self.state[3]);
+                let aspect = F::of_f64(STD_ASPECT_MEASUREMENT);
+                diagonal_covariance([pos * h, pos * h, aspect, pos * h])
+            }
+        };
+        if let Some(score) = score.filter(|_| self.noise.confidence_adaptive) {
+            r *= (F::one() - score.clamp(F::zero(), F::one()))
+                .max(F::of_f64(MIN_CONFIDENCE_NOISE_SCALE));
+        }
+        r
+    }
+
+    /// Records the class of a matched detection and updates `class_id`
+    /// according to `mode`. Ties keep the current class.
+    pub fn update_class(&mut self, class_id: i32, mode: TrackClassMode) {
+        if mode == TrackClassMode::Fixed {
+            return;
+        }
+
+        if self.class_votes.is_empty() {
+            self.class_votes.push((self.class_id, 1));
+        }
+        match self.class_votes.iter_mut().find(|(c, _)| *c == class_id) {
+            Some((_, votes)) => *votes += 1,
+            None => self.class_votes.push((class_id, 1)),
+        }
+
+        let current_votes = self
+            .class_votes
+            .iter()
+            .find(|(c, _)| *c == self.class_id)
+            .map_or(0, |&(_, votes)| votes);
+        if let Some(&(best_class, best_votes)) =
+            self.class_votes.iter().max_by_key(|&&(_, votes)| votes)
+        {
+            if best_votes > current_votes {
+                self.class_id = best_class;
+            }
+        }
     }
 
-    pub fn get_state_bbox(&self) -> [f32; 4] {
-        [self.state[0], self.state[1], self.state[2], self.state[3]]
+    pub fn gallery(&self) -> &FeatureGallery<F> {
+        &self.gallery
+    }
+
+    pub fn update_appearance(&mut self, embedding: &[F], mode: GalleryMode) {
+        self.gallery.add(embedding, mode);
+    }
+
+    /// Warps the state mean and covariance by a camera motion, so tracks
+    /// follow the scene when the camera pans or zooms. On error the track is
+    /// left unchanged.
+    pub fn apply_camera_motion(&mut self, motion: &CameraMotion<F>) -> Result<()> {
+        let (rotation, translation) = motion.linearize(&box_center(&self.get_state_bbox()))?;
+
+        let mut transform = OMatrix::<F, U8, U8>::zeros();
+        match self.motion_model {
+            // Every consecutive pair of state entries is a 2D vector, rotated
+            // by the same linear part.
+            MotionModel::Xyxy | MotionModel::Xywh => {
+                for block in 0..4 {
+                    transform
+                        .fixed_view_mut::<2, 2>(block * 2, block * 2)
+                        .copy_from(&rotation);
+                }
+            }
+            // Aspect ratio is kept, height follows the scale of the motion.
+            MotionModel::Xyah => {
+                let scale = rotation.determinant().abs().sqrt();
+                for block in [0, 2] {
+                    transform
+                        .fixed_view_mut::<2, 2>(block * 2, block * 2)
+                        .copy_from(&rotation);
+                }
+                for i in [2, 6] {
+                    transform[(i, i)] = F::one();
+                }
+                for i in [3, 7] {
+                    transform[(i, i)] = scale;
+                }
+            }
+        }
+
+        self.state = transform * self.state;
+        self.p = transform * self.p * transform.transpose();
+
+        // Translation only moves positions, not sizes or velocities.
+        let position_pairs: &[usize] = match self.motion_model {
+            MotionModel::Xyxy => &[0, 2],
+            MotionModel::Xywh | MotionModel::Xyah => &[0],
+        };
+        for &i in position_pairs {
+            self.state[i] += translation[0];
+            self.state[i + 1] += translation[1];
+        }
+
+        Ok(())
+    }
+
+    /// Checks that `motion` can warp the track after the next
+    /// [`predict`](Self::predict), without changing the track.
+    pub(crate) fn check_camera_motion(&self, motion: &CameraMotion<F>) -> Result<()> {
+        let predicted = state_bbox(&(self.f * self.state), self.motion_model);
+        motion.linearize(&box_center(&predicted)).map(|_| ())
+    }
+
+    pub fn get_state_bbox(&self) -> [F; 4] {
+        state_bbox(&self.state, self.motion_model)
+    }
+}
+
+/// Converts the state back into an `[x1, y1, x2, y2]` box.
+fn state_bbox<F: Float>(s: &OVector<F, U8>, motion_model: MotionModel) -> [F; 4] {
+    let two = F::of_f64(2.0);
+    match motion_model {
+        MotionModel::Xyxy => [s[0], s[1], s[2], s[3]],
+        MotionModel::Xywh => [
+            s[0] - s[2] / two,
+            s[1] - s[3] / two,
+            s[0] + s[2] / two,
+            s[1] + s[3] / two,
+        ],
+        MotionModel::Xyah => {
+            let w = s[2] * s[3];
+            [
+                s[0] - w / two,
+                s[1] - s[3] / two,
+                s[0] + w / two,
+                s[1] + s[3] / two,
+            ]
+        }
+    }
+}
+
+/// Center of an `[x1, y1, x2, y2]` box.
+fn box_center<F: Float>(bbox: &[F; 4]) -> Vector2<F> {
+    let two = F::of_f64(2.0);
+    Vector2::new((bbox[0] + bbox[2]) / two, (bbox[1] + bbox[3]) / two)
+}
+
+/// Whether `bbox` maps to a finite measurement, e.g. XYAH needs a non-zero
+/// height.
+pub(crate) fn has_finite_measurement<F: Float>(bbox: &[F; 4], motion_model: MotionModel) -> bool {
+    to_measurement(bbox, motion_model)
+        .iter()
+        .all(|v| v.is_finite())
+}
+
+/// Converts an `[x1, y1, x2, y2]` box into the measured part of the state.
+fn to_measurement<F: Float>(bbox: &[F; 4], motion_model: MotionModel) -> OVector<F, U4> {
+    let two = F::of_f64(2.0);
+    match motion_model {
+        MotionModel::Xyxy => OVector::<F, U4>::new(bbox[0], bbox[1], bbox[2], bbox[3]),
+        MotionModel::Xywh => {
+            let w = bbox[2] - bbox[0];
+            let h = bbox[3] - bbox[1];
+            OVector::<F, U4>::new(bbox[0] + w / two, bbox[1] + h / two, w, h)
+        }
+        MotionModel::Xyah => {
+            let w = bbox[2] - bbox[0];
+            let h = bbox[3] - bbox[1];
+            OVector::<F, U4>::new(bbox[0] + w / two, bbox[1] + h / two, w / h, h)
+        }
+    }
+}
+
+/// Constant velocity transition: every measured quantity moves by its
+/// velocity once per frame.
+fn transition_matrix<F: Float>() -> OMatrix<F, U8, U8> {
+    let mut f = OMatrix::<F, U8, U8>::identity();
+    for i in 0..4 {
+        f[(i, i + 4)] = F::one();
+    }
+    f
+}
+
+/// Diagonal covariance with standard deviations proportional to box size,
+/// `position` weighting the first four entries and `velocity` the rest.
+fn size_scaled_covariance<F: Float>(w: F, h: F, position: F, velocity: F) -> OMatrix<F, U8, U8> {
+    let (w, h) = (noise_size(w), noise_size(h));
+    let std = [
+        position * w,
+        position * h,
+        position * w,
+        position * h,
+        velocity * w,
+        velocity * h,
+        velocity * w,
+        velocity * h,
+    ];
+    OMatrix::<F, U8, U8>::from_diagonal(&OVector::<F, U8>::from_fn(|i, _| std[i] * std[i]))
+}
+
+/// Like [`size_scaled_covariance`] for the XYAH state, where everything but
+/// the aspect ratio scales with height.
+fn height_scaled_covariance<F: Float>(h: F, position: F, velocity: F) -> OMatrix<F, U8, U8> {
+    let h = noise_size(h);
+    let std = [
+        position * h,
+        position * h,
+        F::of_f64(STD_ASPECT),
+        position * h,
+        velocity * h,
+        velocity * h,
+        F::of_f64(STD_ASPECT_VELOCITY),
+        velocity * h,
+    ];
+    OMatrix::<F, U8, U8>::from_diagonal(&OVector::<F, U8>::from_fn(|i, _| std[i] * std[i]))
+}
+
+/// Box size the noise is proportional to, at least [`MIN_NOISE_SIZE`].
+fn noise_size<F: Float>(size: F) -> F {
+    size.abs().max(F::of_f64(MIN_NOISE_SIZE))
+}
+
+fn diagonal_covariance<F: Float>(std: [F; 4]) -> OMatrix<F, U4, U4> {
+    OMatrix::<F, U4, U4>::from_diagonal(&OVector::<F, U4>::from_fn(|i, _| std[i] * std[i]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIRST: [f64; 4] = [10.0, 20.0, 50.0, 80.0];
+    const SECOND: [f64; 4] = [14.0, 26.0, 56.0, 90.0];
+
+    /// Spawns a track on `FIRST`, then predicts, updates with `SECOND` and
+    /// predicts again.
+    fn predict_update_predict(motion_model: MotionModel) -> KalmanBoxTracker<f64> {
+        let mut track = KalmanBoxTracker::with_motion_model(&FIRST, 0, motion_model);
+        track.predict();
+        track.update(&SECOND).unwrap();
+        track.predict();
+        track
+    }
+
+    /// Expected values come from the float64 Python filters, which solve for
+    /// the gain in another order, hence a few ulps of tolerance.
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() <= 4.0 * f64::EPSILON * expected.abs() + 1e-15,
+            "{actual} != {expected}"
+        );
+    }
+
+    fn assert_filter(
+        track: &KalmanBoxTracker<f64>,
+        state: [f64; 8],
+        variances: [f64; 8],
+        covariances: [f64; 2],
+    ) {
+        for i in 0..8 {
+            assert_close(track.state[i], state[i]);
+            assert_close(track.p[(i, i)], variances[i]);
+        }
+        assert_close(track.p[(0, 4)], covariances[0]);
+        assert_close(track.p[(3, 7)], covariances[1]);
+        assert_eq!(track.p, track.p.transpose());
+    }
+
+    #[test]
+    fn xyxy_matches_trackers_sort_filter() {
+        let track = predict_update_predict(MotionModel::Xyxy);
+        assert_filter(
+            &track,
+            [
+                15.706161137440759,
+                28.559241706161135,
+                58.55924170616113,
+                94.2654028436019,
+                1.8957345971563981,
+                2.843601895734597,
+                2.843601895734597,
+                4.739336492890995,
+            ],
+            [
+                0.7361137440758296,
+                0.7361137440758296,
+                0.7361137440758296,
+                0.7361137440758296,
+                0.5460663507109005,
+                0.5460663507109005,
+                0.5460663507109005,
+                0.5460663507109005,
+            ],
+            [0.5834597156398105, 0.5834597156398105],
+        );
+    }
+
+    #[test]
+    fn xywh_matches_botsort_filter() {
+        let track = predict_update_predict(MotionModel::Xywh);
+        assert_filter(
+            &track,
+            [
+                35.37190082644628,
+                58.59504132231405,
+                42.14876033057851,
+                64.29752066115702,
+                1.0330578512396695,
+                1.6528925619834711,
+                0.4132231404958678,
+                0.8264462809917356,
+            ],
+            [
+                14.499782289461104,
+                32.89801862065433,
+                14.499782289461104,
+                32.89801862065433,
+                5.089218899409193,
+                11.455016093504543,
+                5.089218899409193,
+                11.455016093504543,
+            ],
+            [5.847623966942148, 13.157153925619834],
+        );
+    }
+
+    #[test]
+    fn xyah_matches_bytetrack_filter() {
+        let track = predict_update_predict(MotionModel::Xyah);
+        assert_filter(
+            &track,
+            [
+                35.37190082644628,
+                58.59504132231405,
+                0.6664624180984076,
+                64.29752066115702,
+                1.0330578512396695,
+                1.6528925619834711,
+                -1.0212418200531811e-10,
+                0.8264462809917356,
+            ],
+            [
+                32.89801862065433,
+                32.89801862065433,
+                0.0002960789235678547,
+                32.89801862065433,
+                11.455016093504543,
+                11.455016093504543,
+                2.999999990196079e-10,
+                11.455016093504543,
+            ],
+            [13.157153925619834, 13.157153925619834],
+        );
+    }
+
+    /// Track on `FIRST` after one predict and update, so that it has a
+    /// velocity and a full covariance.
+    fn moving_track(motion_model: MotionModel) -> KalmanBoxTracker<f64> {
+        let mut track = KalmanBoxTracker::with_motion_model(&FIRST, 0, motion_model);
+        track.predict();
+        track.update(&SECOND).unwrap();
+        track
+    }
+
+    const MOTION_MODELS: [MotionModel; 3] =
+        [MotionModel::Xyxy, MotionModel::Xywh, MotionModel::Xyah];
+
+    #[test]
+    fn translation_moves_positions_only() {
+        for motion_model in MOTION_MODELS {
+            let mut track = moving_track(motion_model);
+            let before = track.clone();
+            track
+                .apply_camera_motion(&CameraMotion::Affine([[1.0, 0.0, 5.0], [0.0, 1.0, -3.0]]))
+                .unwrap();
+
+            let (old, new) = (before.get_state_bbox(), track.get_state_bbox());
+            for i in 0..4 {
+                let shift = if i % 2 == 0 { 5.0 } else { -3.0 };
+                assert!((new[i] - old[i] - shift).abs() < 1e-9, "{motion_model:?}");
+            }
+            assert_eq!(
+                track.state.fixed_rows::<4>(4),
+                before.state.fixed_rows::<4>(4)
+            );
+            assert_eq!(track.p, before.p);
+        }
+    }
+
+    #[test]
+    fn scaling_scales_state_and_covariance() {
+        let scale_affine = CameraMotion::Affine([[2.0, 0.0, 0.0], [0.0, 2.0, 0.0]]);
+        let scale_homography =
+            CameraMotion::Homography([[2.0, 0.0, 0.0], [0.0, 2.0, 0.0], [0.0, 0.0, 1.0]]);
+        for motion_model in MOTION_MODELS {
+            // The aspect ratio and its velocity are unchanged by a uniform scale.
+            let factors = match motion_model {
+                MotionModel::Xyah => [2.0, 2.0, 1.0, 2.0, 2.0, 2.0, 1.0, 2.0],
+                _ => [2.0; 8],
+            };
+            for motion in [scale_affine, scale_homography] {
+                let mut track = moving_track(motion_model);
+                let before = track.clone();
+                track.apply_camera_motion(&motion).unwrap();
+                for i in 0..8 {
+                    assert_close(track.state[i], before.state[i] * factors[i]);
+                    for j in 0..8 {
+                        assert_close(track.p[(i, j)], before.p[(i, j)] * factors[i] * factors[j]);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn homography_moves_the_center_to_its_projection() {
+        let m = [[1.1, 0.05, 4.0], [-0.02, 0.95, -3.0], [1e-4, -2e-4, 1.0]];
+        for motion_model in [MotionModel::Xywh, MotionModel::Xyah] {
+            let mut track = moving_track(motion_model);
+            let (x, y) = (track.state[0], track.state[1]);
+            let w = m[2][0] * x + m[2][1] * y + m[2][2];
+            let expected = [
+                (m[0][0] * x + m[0][1] * y + m[0][2]) / w,
+                (m[1][0] * x + m[1][1] * y + m[1][2]) / w,
+            ];
+
+            track
+                .apply_camera_motion(&CameraMotion::Homography(m))
+                .unwrap();
+            assert_close(track.state[0], expected[0]);
+            assert_close(track.state[1], expected[1]);
+            for i in 0..8 {
+                for j in 0..i {
+                    assert_close(track.p[(i, j)], track.p[(j, i)]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rejected_camera_motion_leaves_the_track_unchanged() {
+        let mut track = moving_track(MotionModel::Xywh);
+        let before = track.clone();
+        let behind = CameraMotion::Homography([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, -1.0]]);
+        assert!(track.check_camera_motion(&behind).is_err());
+        assert_eq!(
+            track.apply_camera_motion(&behind),
+            Err(TrackerError::InvalidCameraMotion)
+        );
+        assert_eq!(track.state, before.state);
+        assert_eq!(track.p, before.p);
+    }
+
+    #[test]
+    fn zero_size_boxes_keep_the_filter_solvable() {
+        let boxes: [[f32; 4]; 2] = [[100.0, 100.0, 100.0, 200.0], [100.0, 150.0, 150.0, 150.0]];
+        for (bbox, motion_model) in [
+            (boxes[0], MotionModel::Xywh),
+            (boxes[1], MotionModel::Xywh),
+            (boxes[0], MotionModel::Xyah),
+        ] {
+            let mut track = KalmanBoxTracker::with_motion_model(&bbox, 0, motion_model);
+            for _ in 0..10 {
+                track.predict();
+                assert!(track.mahalanobis_distance(&bbox).unwrap().is_finite());
+                track.update(&bbox).unwrap();
+            }
+            for (actual, expected) in track.get_state_bbox().iter().zip(bbox) {
+                assert!((actual - expected).abs() < 1e-3, "{motion_model:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn majority_vote_switches_class_on_more_votes_only() {
+        let mut track = KalmanBoxTracker::<f64>::new(&FIRST, 0);
+        track.update_class(1, TrackClassMode::Fixed);
+        assert_eq!(track.class_id, 0);
+        assert!(track.class_votes.is_empty());
+
+        // The spawning class counts as a vote, a tie keeps it.
+        track.update_class(1, TrackClassMode::MajorityVote);
+        assert_eq!(track.class_id, 0);
+        track.update_class(1, TrackClassMode::MajorityVote);
+        assert_eq!(track.class_id, 1);
+        for _ in 0..2 {
+            track.update_class(2, TrackClassMode::MajorityVote);
+        }
+        assert_eq!(track.class_id, 1);
+        track.update_class(2, TrackClassMode::MajorityVote);
+        assert_eq!(track.class_id, 2);
+    }
+
+    #[test]
+    fn noise_config_sets_process_and_measurement_noise() {
+        let noise = KalmanNoiseConfig::default()
+            .with_process_noise(0.5)
+            .with_measurement_noise(0.3)
+            .with_std_weight_position(0.1)
+            .with_std_weight_velocity(0.01);
+        // 40 px wide and 60 px high.
+        let xyxy = KalmanBoxTracker::with_noise(&FIRST, 0, MotionModel::Xyxy, noise);
+        let xywh = KalmanBoxTracker::with_noise(&FIRST, 0, MotionModel::Xywh, noise);
+        let xyah = KalmanBoxTracker::with_noise(&FIRST, 0, MotionModel::Xyah, noise);
+
+        assert_eq!(
+            xyxy.measurement_noise(None),
+            OMatrix::<f64, U4, U4>::identity() * 0.3
+        );
+        assert_eq!(
+            xywh.measurement_noise(None).diagonal().as_slice(),
+            [4.0 * 4.0, 6.0 * 6.0, 4.0 * 4.0, 6.0 * 6.0]
+        );
+        assert_eq!(
+            xyah.measurement_noise(None).diagonal().as_slice(),
+            [36.0, 36.0, STD_ASPECT_MEASUREMENT.powi(2), 36.0]
+        );
+
+        let process_noise = |mut track: KalmanBoxTracker<f64>| {
+            let propagated = track.f * track.p * track.f.transpose();
+            track.predict();
+            track.p - propagated
+        };
+        assert_eq!(
+            process_noise(xyxy),
+            OMatrix::<f64, U8, U8>::identity() * 0.5
+        );
+        let expected = [16.0, 36.0, 16.0, 36.0, 0.16, 0.36, 0.16, 0.36];
+        for (actual, expected) in process_noise(xywh).diagonal().iter().zip(expected) {
+            assert_close(*actual, expected);
+        }
+    }
+
+    #[test]
+    fn confidence_adaptive_noise_scales_with_score_down_to_a_floor() {
+        let noise = KalmanNoiseConfig::default().with_confidence_adaptive(true);
+        for motion_model in MOTION_MODELS {
+            let track = KalmanBoxTracker::with_noise(&FIRST, 0, motion_model, noise);
+            let r = track.measurement_noise(None);
+            assert_eq!(track.measurement_noise(Some(0.75)), r * 0.25);
+            assert_eq!(track.measurement_noise(Some(1.0)), r * 0.01);
+            assert_eq!(track.measurement_noise(Some(2.0)), r * 0.01);
+            assert_eq!(track.measurement_noise(Some(-1.0)), r);
+
+            let fixed = KalmanBoxTracker::with_motion_model(&FIRST, 0, motion_model);
+            assert_eq!(fixed.measurement_noise(Some(0.75)), r);
+        }
+    }
+
+    #[test]
+    fn confident_detections_pull_the_adaptive_filter_harder() {
+        let noise = KalmanNoiseConfig::default().with_confidence_adaptive(true);
+        let updated = |score| {
+            let mut track = KalmanBoxTracker::with_noise(&FIRST, 0, MotionModel::Xywh, noise);
+            track.predict();
+            track.update_with_score(&SECOND, Some(score)).unwrap();
+            track
+        };
+        let error = |track: &KalmanBoxTracker<f64>| {
+            let bbox = track.get_state_bbox();
+            (0..4).map(|i| (bbox[i] - SECOND[i]).abs()).sum::<f64>()
+        };
+
+        let (low, high, certain) = (updated(0.3), updated(0.9), updated(1.0));
+        assert!(error(&high) < error(&low));
+        assert!(error(&certain) < error(&high));
+        // The floor keeps the innovation covariance invertible after a
+        // detection of score 1.0.
+        assert!(certain.innovation_covariance().cholesky().is_some());
+        assert!(certain.state.iter().all(|v| v.is_finite()));
     }
 }
@@ -0,0 +1,62 @@
+//! Rust port of the ByteTrack and SORT multi-object trackers from the Python
+//! [trackers](https://github.com/roboflow/trackers) package, plus OC-SORT and
+//! BoT-SORT.
+//!
+//! Trackers consume per-frame [`Detection`]s and return
+//! [`TrackedDetection`]s carrying a stable `tracker_id`.
+
+mod appearance;
+mod assignment;
+mod botsort;
+mod bytetrack;
+mod cmc;
+mod config;
+mod cost;
+mod detection;
+mod error;
+mod events;
+mod float;
+mod iou;
+mod kalman;
+mod multistream;
+mod ocsort;
+mod simd;
+mod snapshot;
+mod sort;
+mod spatial;
+mod tracker;
+mod utils;
+
+pub use appearance::{
+    cosine_distance, fused_similarity, AppearanceConfig, FeatureGallery, GalleryMode,
+};
+pub use assignment::AssignmentSolver;
+pub use botsort::BotSortTracker;
+pub use bytetrack::ByteTrackTracker;
+pub use cmc::CameraMotion;
+pub use config::{BotSortConfig, ByteTrackConfig, OcSortConfig, SortConfig, TrackClassMode};
+pub use cost::{
+    AssociationCost, AssociationInput, CenterDistanceCost, EmbeddingCost, IouCost, MahalanobisCost,
+    WeightedCost,
+};
+pub use detection::{Detection, InputData, OutputData, TrackedDetection};
+pub use error::{ConfigError, Result, TrackerError};
+pub use events::{TrackEvent, TrackEventKind};
+pub use float::Float;
+pub use iou::{
+    compute_buffered_iou, compute_ciou, compute_diou, compute_giou, compute_iou, compute_iou_batch,
+    IouMetric,
+};
+pub use kalman::{
+    KalmanBoxTracker, KalmanNoiseConfig, MotionModel, TrackState, CHI_SQUARE_95_4DOF,
+};
+pub use multistream::MultiStreamTracker;
+pub use ocsort::OcSortTracker;
+pub use snapshot::{ByteTrackSnapshot, SNAPSHOT_VERSION};
+pub use sort::SortTracker;
+pub use spatial::{compute_iou_sparse, SparseSimilarity};
+pub use tracker::{TrackInfo, Tracker, TrackerConfig};
+pub use utils::{
+    get_alive_trackers, get_associated_indices, get_class_associated_indices,
+    get_sparse_associated_indices, linear_sum_assignment, LsapWorkspace,
+};
@@ -0,0 +1,212 @@
+use std::borrow::Borrow;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::detection::{Detection, TrackedDetection};
+use crate::error::{Result, TrackerError};
+use crate::float::Float;
+use crate::tracker::{Tracker, TrackerConfig};
+
+/// One tracker per video stream, keyed by stream ID and built from a shared
+/// [`TrackerConfig`]. Streams are independent: each numbers its tracks from
+/// zero and keeps its own frame count.
+///
+/// [`update_batch`](Self::update_batch) advances several streams at once, in
+/// parallel with the `parallel` feature.
+pub struct MultiStreamTracker<K, F: Float = f32> {
+    config: TrackerConfig,
+    streams: HashMap<K, Box<dyn Tracker<F>>>,
+}
+
+impl<K: Eq + Hash + Clone, F: Float> MultiStreamTracker<K, F> {
+    /// Creates a pool without streams after validating `config`.
+    pub fn new(config: TrackerConfig) -> Result<Self> {
+        config.validate()?;
+
+        Ok(Self {
+            config,
+            streams: HashMap::new(),
+        })
+    }
+
+    pub fn config(&self) -> &TrackerConfig {
+        &self.config
+    }
+
+    /// Creates a tracker for `stream_id` unless the stream already exists.
+    /// Returns whether it was created.
+    pub fn add_stream(&mut self, stream_id: K) -> Result<bool> {
+        if self.streams.contains_key(&stream_id) {
+            return Ok(false);
+        }
+        self.streams.insert(stream_id, self.config.build()?);
+        Ok(true)
+    }
+
+    /// Drops the stream and returns its tracker, `None` if it did not exist.
+    pub fn remove_stream<Q>(&mut self, stream_id: &Q) -> Option<Box<dyn Tracker<F>>>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.streams.remove(stream_id)
+    }
+
+    pub fn contains_stream<Q>(&self, stream_id: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.streams.contains_key(stream_id)
+    }
+
+    /// Tracker of the stream, e.g. to read its tracks or events.
+    pub fn stream<Q>(&self, stream_id: &Q) -> Option<&dyn Tracker<F>>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.streams.get(stream_id).map(|tracker| tracker.as_ref())
+    }
+
+    pub fn stream_mut<Q>(&mut self, stream_id: &Q) -> Option<&mut (dyn Tracker<F> + 'static)>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.streams
+            .get_mut(stream_id)
+            .map(|tracker| tracker.as_mut())
+    }
+
+    /// IDs of all streams, in arbitrary order.
+    pub fn stream_ids(&self) -> impl Iterator<Item = &K> {
+        self.streams.keys()
+    }
+
+    pub fn len(&self) -> usize {
+        self.streams.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.streams.is_empty()
+    }
+
+    /// Advances one stream by a frame, creating the stream on its first
+    /// frame. See [`Tracker::update`].
+    pub fn update(
+        &mut self,
+        stream_id: &K,
+        detections: &[Detection<F>],
+    ) -> Result<Vec<TrackedDetection<F>>> {
+        self.tracker_or_insert(stream_id)?.update(detections)
+    }
+
+    /// Same as [`update`](Self::update), writing the tracked detections to
+    /// `output` like [`Tracker::update_into`].
+    pub fn update_into(
+        &mut self,
+        stream_id: &K,
+        detections: &[Detection<F>],
+        output: &mut Vec<TrackedDetection<F>>,
+    ) -> Result<()> {
+        self.tracker_or_insert(stream_id)?
+            .update_into(detections, output)
+    }
+
+    /// Advances every stream of `frames` by one frame, creating streams on
+    /// their first frame. Returns the tracked detections in the order of
+    /// `frames`.
+    ///
+    /// With the `parallel` feature the streams are updated on the rayon
+    /// thread pool. A stream may appear only once per batch, otherwise
+    /// [`TrackerError::DuplicateStream`] is returned before any update.
+    /// Every stream is updated even if another one fails; the error of the
+    /// earliest failing stream in `frames` is then returned, with or without
+    /// the `parallel` feature.
+    pub fn update_batch<D>(&mut self, frames: &[(K, D)]) -> Result<Vec<Vec<TrackedDetection<F>>>>
+    where
+        D: AsRef<[Detection<F>]> + Sync,
+    {
+        let mut positions = HashMap::with_capacity(frames.len());
+        for (index, (stream_id, _)) in frames.iter().enumerate() {
+            if positions.insert(stream_id, index).is_some() {
+                return Err(TrackerError::DuplicateStream { index });
+            }
+        }
+        for (stream_id, _) in frames {
+            self.tracker_or_insert(stream_id)?;
+        }
+
+        let mut trackers: Vec<Option<&mut Box<dyn Tracker<F>>>> =
+            (0..frames.len()).map(|_| None).collect();
+        for (stream_id, tracker) in self.streams.iter_mut() {
+            if let Some(&index) = positions.get(stream_id) {
+                trackers[index] = Some(tracker);
+            }
+        }
+        let jobs: Vec<_> = trackers
+            .into_iter()
+            .zip(frames)
+            .filter_map(|(tracker, (_, detections))| Some((tracker?, detections.as_ref())))
+            .collect();
+
+        #[cfg(feature = "parallel")]
+        let jobs = jobs.into_par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let jobs = jobs.into_iter();
+
+        // Collected in input order first, so the returned error does not
+        // depend on which thread finished first.
+        let results: Vec<_> = jobs
+            .map(|(tracker, detections)| tracker.update(detections))
+            .collect();
+        results.into_iter().collect()
+    }
+
+    fn tracker_or_insert(&mut self, stream_id: &K) -> Result<&mut Box<dyn Tracker<F>>> {
+        match self.streams.entry(stream_id.clone()) {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => Ok(entry.insert(self.config.build()?)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SortConfig;
+
+    #[test]
+    fn update_batch_returns_error_of_earliest_failing_stream() {
+        let mut pool =
+            MultiStreamTracker::<usize>::new(TrackerConfig::Sort(SortConfig::default())).unwrap();
+        let valid = Detection::new([10.0, 10.0, 50.0, 90.0], 0, 0.9);
+        let invalid = Detection::new([f32::NAN, 10.0, 50.0, 90.0], 0, 0.9);
+
+        // Stream `k` fails at detection index `k` for odd `k`.
+        let frames: Vec<(usize, Vec<Detection>)> = (0..12)
+            .map(|k| {
+                let mut detections = vec![valid.clone(); k + 1];
+                if k % 2 == 1 {
+                    detections[k] = invalid.clone();
+                }
+                (k, detections)
+            })
+            .collect();
+        for _ in 0..10 {
+            assert_eq!(
+                pool.update_batch(&frames).unwrap_err(),
+                TrackerError::InvalidDetection { index: 1 }
+            );
+        }
+
+        assert_eq!(pool.len(), 12);
+        assert!(!pool.stream(&10).unwrap().active_tracks().is_empty());
+        assert!(pool.stream(&11).unwrap().active_tracks().is_empty());
+    }
+}
@@ -0,0 +1,650 @@
+use std::collections::VecDeque;
+
+use crate::config::OcSortConfig;
+use crate::detection::{check_detections, Detection, TrackedDetection};
+use crate::error::Result;
+use crate::events::{record_events, TrackEvent};
+use crate::float::Float;
+use crate::iou::compute_iou;
+use crate::kalman::KalmanBoxTracker;
+use crate::tracker::{active_tracks, lost_tracks, TrackInfo, Tracker, TrackerConfig};
+use crate::utils::{prune_tracks, LsapWorkspace};
+
+/// Kalman track extended with the observation history OC-SORT relies on.
+#[derive(Clone)]
+struct OcSortTrack<F: Float> {
+    kalman: KalmanBoxTracker<F>,
+    /// Number of predictions since the track was spawned.
+    age: i32,
+    /// Observations from the last `delta_t` frames, keyed by `age`.
+    observations: VecDeque<(i32, [F; 4])>,
+    last_observation: Option<(i32, [F; 4])>,
+    /// Normalized `[dy, dx]` direction of motion between observations.
+    velocity: Option<[F; 2]>,
+    /// Filter saved on the first missed frame, rolled back by ORU.
+    frozen: Option<KalmanBoxTracker<F>>,
+}
+
+impl<F: Float> OcSortTrack<F> {
+    fn new(bbox: &[F; 4], class_id: i32, config: &OcSortConfig) -> Self {
+        Self {
+            kalman: KalmanBoxTracker::with_noise(bbox, class_id, config.motion_model, config.noise),
+            age: 0,
+            observations: VecDeque::new(),
+            last_observation: None,
+            velocity: None,
+            frozen: None,
+        }
+    }
+
+    fn predict(&mut self, delta_t: i32) {
+        self.kalman.predict();
+        self.age += 1;
+
+        while matches!(self.observations.front(), Some(&(age, _)) if age < self.age - delta_t) {
+            self.observations.pop_front();
+        }
+    }
+
+    /// Observation `delta_t` frames ago, or the closest newer one, falling
+    /// back to the latest observation.
+    fn previous_observation(&self, delta_t: i32) -> Option<[F; 4]> {
+        for dt in (1..=delta_t).rev() {
+            if let Some(&(_, bbox)) = self
+                .observations
+                .iter()
+                .find(|&&(age, _)| age == self.age - dt)
+            {
+                return Some(bbox);
+            }
+        }
+        self.last_observation.map(|(_, bbox)| bbox)
+    }
+
+    fn mark_missed(&mut self) {
+        if self.frozen.is_none()
+            && self.last_observation.is_some()
+            && self.kalman.time_since_update == 1
+        {
+            self.frozen = Some(self.kalman.clone());
+        }
+    }
+
+    fn update(&mut self, bbox: &[F; 4], score: F, delta_t: i32) -> Result<()> {
+        if let Some(frozen) = self.frozen.take() {
+            self.reupdate(frozen, bbox)?;
+        }
+
+        if self.last_observation.is_some() {
+            if let Some(previous) = self.previous_observation(delta_t) {
+                self.velocity = Some(speed_direction(&previous, bbox));
+            }
+        }
+
+        self.kalman.update_with_score(bbox, Some(score))?;
+        self.last_observation = Some((self.age, *bbox));
+        self.observations.push_back((self.age, *bbox));
+
+        Ok(())
+    }
+
+    /// Observation-centric re-update: rolls the filter back to the first
+    /// missed frame and replays it along a virtual trajectory linearly
+    /// interpolated between the last observation and `bbox`. Virtual boxes
+    /// only correct the filter and do not count as observations.
+    fn reupdate(&mut self, frozen: KalmanBoxTracker<F>, bbox: &[F; 4]) -> Result<()> {
+        let Some((last_age, last_bbox)) = self.last_observation else {
+            return Ok(());
+        };
+
+        self.kalman.roll_back(frozen);
+        let time_gap = self.age - last_age;
+        for step in 1..time_gap {
+            let t = F::of_f64(step as f64 / time_gap as f64);
+            let virtual_bbox = [
+                last_bbox[0] + (bbox[0] - last_bbox[0]) * t,
+                last_bbox[1] + (bbox[1] - last_bbox[1]) * t,
+                last_bbox[2] + (bbox[2] - last_bbox[2]) * t,
+                last_bbox[3] + (bbox[3] - last_bbox[3]) * t,
+            ];
+            self.kalman.correct(&virtual_bbox, None)?;
+            self.kalman.predict();
+        }
+
+        Ok(())
+    }
+}
+
+/// Normalized `[dy, dx]` direction between the centers of two boxes.
+fn speed_direction<F: Float>(from: &[F; 4], to: &[F; 4]) -> [F; 2] {
+    let two = F::of_f64(2.0);
+    let dx = (to[0] + to[2]) / two - (from[0] + from[2]) / two;
+    let dy = (to[1] + to[3]) / two - (from[1] + from[3]) / two;
+    let norm = (dx * dx + dy * dy).sqrt() + F::of_f64(1e-6);
+    [dy / norm, dx / norm]
+}
+
+/// OC-SORT tracker: SORT with observation-centric re-update (ORU),
+/// momentum (OCM) and recovery (OCR) for robustness to occlusion.
+pub struct OcSortTracker<F: Float = f32> {
+    config: OcSortConfig,
+    maximum_frames_without_update: i32,
+    next_tracker_id: i32,
+    frame_count: u64,
+
+    // Buffers for memory reuse
+    tracks: Vec<OcSortTrack<F>>,
+    /// Tracks at the start of the frame, restored when it fails.
+    saved_tracks: Vec<OcSortTrack<F>>,
+    detection_indices: Vec<usize>,
+    detection_boxes: Vec<[F; 4]>,
+    detection_scores: Vec<F>,
+    predicted_boxes: Vec<[F; 4]>,
+    iou_matrix: Vec<F>,
+    cost_matrix: Vec<F>,
+    matched_indices: Vec<(usize, usize)>,
+    row_counts: Vec<usize>,
+    col_counts: Vec<usize>,
+    assignment: Vec<Option<usize>>,
+    lsap: LsapWorkspace<F>,
+    unmatched_track_indices: Vec<usize>,
+    unmatched_det_indices: Vec<usize>,
+    remaining_track_indices: Vec<usize>,
+    remaining_det_indices: Vec<usize>,
+    detection_tracks: Vec<Option<usize>>,
+    removed_tracks: Vec<KalmanBoxTracker<F>>,
+    events: Vec<TrackEvent<F>>,
+}
+
+impl<F: Float> OcSortTracker<F> {
+    /// Creates a tracker after validating `config`.
+    pub fn new(config: OcSortConfig) -> Result<Self> {
+        config.validate()?;
+
+        Ok(Self {
+            maximum_frames_without_update: config.maximum_frames_without_update(),
+            config,
+            next_tracker_id: 0,
+            frame_count: 0,
+            tracks: Vec::new(),
+            saved_tracks: Vec::new(),
+            detection_indices: Vec::new(),
+            detection_boxes: Vec::new(),
+            detection_scores: Vec::new(),
+            predicted_boxes: Vec::new(),
+            iou_matrix: Vec::new(),
+            cost_matrix: Vec::new(),
+            matched_indices: Vec::new(),
+            row_counts: Vec::new(),
+            col_counts: Vec::new(),
+            assignment: Vec::new(),
+            lsap: LsapWorkspace::new(),
+            unmatched_track_indices: Vec::new(),
+            unmatched_det_indices: Vec::new(),
+            remaining_track_indices: Vec::new(),
+            remaining_det_indices: Vec::new(),
+            detection_tracks: Vec::new(),
+            removed_tracks: Vec::new(),
+            events: Vec::new(),
+        })
+    }
+
+    pub fn config(&self) -> &OcSortConfig {
+        &self.config
+    }
+
+    /// First association round: IoU plus the OCM velocity direction
+    /// consistency term, weighted by detection score.
+    fn associate_with_momentum(&mut self, detections: &[Detection<F>]) {
+        let n_tracks = self.tracks.len();
+        let n_dets = self.detection_boxes.len();
+
+        self.iou_matrix.clear();
+        self.cost_matrix.clear();
+        for track_idx in 0..n_tracks {
+            let track = &self.tracks[track_idx];
+            let previous = track.previous_observation(self.config.delta_t);
+            let velocity = track.velocity.unwrap_or([F::zero(); 2]);
+
+            for det_idx in 0..n_dets {
+                let det_box = &self.detection_boxes[det_idx];
+                let iou = compute_iou(&self.predicted_boxes[track_idx], det_box);
+
+                let angle_cost = match previous {
+                    Some(previous) => {
+                        let [dy, dx] = speed_direction(&previous, det_box);
+                        let cos = (velocity[0] * dy + velocity[1] * dx).clamp(-F::one(), F::one());
+                        let angle = (F::frac_pi_2() - cos.acos().abs()) / F::pi();
+                        let score = detections[self.detection_indices[det_idx]].score;
+                        angle * F::of_f64(self.config.direction_consistency_weight) * score
+                    }
+                    None => F::zero(),
+                };
+
+                self.iou_matrix.push(iou);
+                self.cost_matrix.push(-(iou + angle_cost));
+            }
+        }
+
+        if !self.take_unique_candidates(n_tracks, n_dets) {
+            self.match_indices(n_tracks, n_dets);
+        }
+    }
+
+    /// Takes the pairs above the IoU threshold when there are some and every
+    /// track and detection has at most one of them, as the first round of
+    /// OC-SORT does. Returns `false`, leaving `matched_indices` empty,
+    /// otherwise.
+    fn take_unique_candidates(&mut self, n_rows: usize, n_cols: usize) -> bool {
+        let minimum_iou_threshold = F::of_f64(self.config.minimum_iou_threshold);
+        let is_candidate = |iou: F| iou > minimum_iou_threshold;
+        self.matched_indices.clear();
+        self.row_counts.clear();
+        self.row_counts.resize(n_rows, 0);
+        self.col_counts.clear();
+        self.col_counts.resize(n_cols, 0);
+        for row in 0..n_rows {
+            for col in 0..n_cols {
+                if is_candidate(self.iou_matrix[row * n_cols + col]) {
+                    self.row_counts[row] += 1;
+                    self.col_counts[col] += 1;
+                }
+            }
+        }
+
+        let at_most_one = |counts: &[usize]| counts.iter().max() == Some(&1);
+        if !at_most_one(&self.row_counts) || !at_most_one(&self.col_counts) {
+            return false;
+        }
+        for row in 0..n_rows {
+            for col in 0..n_cols {
+                if is_candidate(self.iou_matrix[row * n_cols + col]) {
+                    self.matched_indices.push((row, col));
+                }
+            }
+        }
+        true
+    }
+
+    /// Solves the assignment on `cost_matrix` and keeps pairs whose IoU
+    /// reaches the threshold.
+    fn match_indices(&mut self, n_rows: usize, n_cols: usize) {
+        let minimum_iou_threshold = F::of_f64(self.config.minimum_iou_threshold);
+        self.matched_indices.clear();
+        if n_rows == 0 || n_cols == 0 {
+            return;
+        }
+
+        self.lsap
+            .solve_unchecked(&self.cost_matrix, n_rows, n_cols, &mut self.assignment);
+        for (row, &opt_col) in self.assignment.iter().enumerate() {
+            if let Some(col) = opt_col {
+                if self.iou_matrix[row * n_cols + col] >= minimum_iou_threshold {
+                    self.matched_indices.push((row, col));
+                }
+            }
+        }
+    }
+
+    /// Second association round (OCR): unmatched tracks are matched by IoU
+    /// between their last observation and the remaining detections, always
+    /// through the assignment solver.
+    fn recover(&mut self) {
+        let n_tracks = self.unmatched_track_indices.len();
+        let n_dets = self.unmatched_det_indices.len();
+
+        self.iou_matrix.clear();
+        self.cost_matrix.clear();
+        for &track_idx in &self.unmatched_track_indices {
+            let last = self.tracks[track_idx].last_observation;
+            for &det_idx in &self.unmatched_det_indices {
+                let iou = match last {
+                    Some((_, bbox)) => compute_iou(&bbox, &self.detection_boxes[det_idx]),
+                    None => F::zero(),
+                };
+                self.iou_matrix.push(iou);
+                self.cost_matrix.push(-iou);
+            }
+        }
+
+        let max_iou = self.iou_matrix.iter().copied().fold(F::zero(), F::max);
+        if max_iou <= F::of_f64(self.config.minimum_iou_threshold) {
+            self.matched_indices.clear();
+            return;
+        }
+
+        self.match_indices(n_tracks, n_dets);
+        for pair in &mut self.matched_indices {
+            *pair = (
+                self.unmatched_track_indices[pair.0],
+                self.unmatched_det_indices[pair.1],
+            );
+        }
+    }
+
+    fn apply_matches(&mut self) -> Result<()> {
+        for &(track_idx, det_idx) in &self.matched_indices {
+            self.tracks[track_idx].update(
+                &self.detection_boxes[det_idx],
+                self.detection_scores[det_idx],
+                self.config.delta_t,
+            )?;
+            self.detection_tracks[det_idx] = Some(track_idx);
+        }
+
+        Ok(())
+    }
+
+    fn refresh_unmatched(&mut self) {
+        self.remaining_track_indices.clear();
+        for &track_idx in &self.unmatched_track_indices {
+            if !self.matched_indices.iter().any(|&(t, _)| t == track_idx) {
+                self.remaining_track_indices.push(track_idx);
+            }
+        }
+        std::mem::swap(
+            &mut self.unmatched_track_indices,
+            &mut self.remaining_track_indices,
+        );
+
+        self.remaining_det_indices.clear();
+        for &det_idx in &self.unmatched_det_indices {
+            if !self.matched_indices.iter().any(|&(_, d)| d == det_idx) {
+                self.remaining_det_indices.push(det_idx);
+            }
+        }
+        std::mem::swap(
+            &mut self.unmatched_det_indices,
+            &mut self.remaining_det_indices,
+        );
+    }
+
+    /// Predicts the tracks and matches them with the high-confidence
+    /// `detections` by momentum, then by recovery.
+    fn match_detections(&mut self, detections: &[Detection<F>]) -> Result<()> {
+        self.detection_indices.clear();
+        self.detection_boxes.clear();
+        self.detection_scores.clear();
+        for (idx, det) in detections.iter().enumerate() {
+            if det.score >= F::of_f64(self.config.high_conf_det_threshold) {
+                self.detection_indices.push(idx);
+                self.detection_boxes.push(det.box_coords);
+                self.detection_scores.push(det.score);
+            }
+        }
+
+        self.predicted_boxes.clear();
+        for track in &mut self.tracks {
+            track.predict(self.config.delta_t);
+            self.predicted_boxes.push(track.kalman.get_state_bbox());
+        }
+
+        self.detection_tracks.clear();
+        self.detection_tracks
+            .resize(self.detection_boxes.len(), None);
+
+        self.unmatched_track_indices.clear();
+        self.unmatched_track_indices.extend(0..self.tracks.len());
+        self.unmatched_det_indices.clear();
+        self.unmatched_det_indices
+            .extend(0..self.detection_boxes.len());
+
+        self.associate_with_momentum(detections);
+        self.apply_matches()?;
+        self.refresh_unmatched();
+
+        if !self.unmatched_track_indices.is_empty() && !self.unmatched_det_indices.is_empty() {
+            self.recover();
+            self.apply_matches()?;
+            self.refresh_unmatched();
+        }
+
+        for &track_idx in &self.unmatched_track_indices {
+            self.tracks[track_idx].mark_missed();
+        }
+
+        Ok(())
+    }
+
+    /// Advances the tracker by one frame. Errors are described on
+    /// [`Tracker::update`].
+    pub fn update(&mut self, detections: &[Detection<F>]) -> Result<Vec<TrackedDetection<F>>> {
+        check_detections(detections, self.config.motion_model)?;
+
+        let frame_index = self.frame_count;
+        if self.tracks.is_empty() && detections.is_empty() {
+            self.frame_count += 1;
+            self.events.clear();
+            self.removed_tracks.clear();
+            return Ok(Vec::new());
+        }
+
+        // A singular covariance fails after the tracks have changed, the
+        // saved copy restores them.
+        self.saved_tracks.clone_from(&self.tracks);
+        if let Err(error) = self.match_detections(detections) {
+            std::mem::swap(&mut self.tracks, &mut self.saved_tracks);
+            return Err(error);
+        }
+        self.frame_count += 1;
+        self.events.clear();
+
+        let mut tracked: Vec<TrackedDetection<F>> = detections
+            .iter()
+            .enumerate()
+            .map(|(idx, det)| TrackedDetection::new(det, idx, -1, None))
+            .collect();
+
+        for (det_idx, opt_track) in self.detection_tracks.iter().enumerate() {
+            let Some(track_idx) = *opt_track else {
+                continue;
+            };
+            let kalman = &mut self.tracks[track_idx].kalman;
+            if kalman.number_of_successful_updates >= self.config.minimum_consecutive_frames
+                && kalman.tracker_id == -1
+            {
+                kalman.confirm(self.next_tracker_id);
+                self.next_tracker_id += 1;
+            }
+
+            let output = &mut tracked[self.detection_indices[det_idx]];
+            output.tracker_id = kalman.tracker_id;
+            output.predicted_box = Some(self.predicted_boxes[track_idx]);
+        }
+
+        for &det_idx in &self.unmatched_det_indices {
+            let detection = &detections[self.detection_indices[det_idx]];
+            self.tracks.push(OcSortTrack::new(
+                &detection.box_coords,
+                detection.class_id,
+                &self.config,
+            ));
+        }
+
+        prune_tracks(
+            &mut self.tracks,
+            |track| &mut track.kalman,
+            self.config.minimum_consecutive_frames,
+            self.maximum_frames_without_update,
+            &mut self.removed_tracks,
+        );
+
+        record_events(
+            frame_index,
+            self.tracks.iter_mut().map(|t| &mut t.kalman),
+            &self.removed_tracks,
+            &mut self.events,
+        );
+
+        Ok(tracked)
+    }
+}
+
+impl<F: Float> Tracker<F> for OcSortTracker<F> {
+    fn name(&self) -> &'static str {
+        "OC-SORT"
+    }
+
+    fn update(&mut self, detections: &[Detection<F>]) -> Result<Vec<TrackedDetection<F>>> {
+        OcSortTracker::update(self, detections)
+    }
+
+    fn reset(&mut self) {
+        self.tracks.clear();
+        self.removed_tracks.clear();
+        self.events.clear();
+        self.next_tracker_id = 0;
+        self.frame_count = 0;
+    }
+
+    fn active_tracks(&self) -> Vec<TrackInfo<F>> {
+        active_tracks(self.tracks.iter().map(|t| &t.kalman))
+    }
+
+    fn lost_tracks(&self) -> Vec<TrackInfo<F>> {
+        lost_tracks(self.tracks.iter().map(|t| &t.kalman))
+    }
+
+    fn removed_tracks(&self) -> Vec<TrackInfo<F>> {
+        self.removed_tracks.iter().map(TrackInfo::from).collect()
+    }
+
+    fn events(&self) -> &[TrackEvent<F>] {
+        &self.events
+    }
+
+    fn config(&self) -> TrackerConfig {
+        TrackerConfig::OcSort(self.config.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reupdate_counts_only_real_observations() {
+        let mut tracker = OcSortTracker::<f32>::new(OcSortConfig::default()).unwrap();
+        let detection = [Detection::new([100.0, 100.0, 150.0, 200.0], 0, 0.9)];
+
+        for _ in 0..3 {
+            tracker.update(&detection).unwrap();
+        }
+        for _ in 0..4 {
+            tracker.update(&[]).unwrap();
+        }
+        tracker.update(&detection).unwrap();
+
+        let tracks = tracker.active_tracks();
+        assert_eq!(tracks.len(), 1);
+        assert_eq!(tracks[0].time_since_update, 0);
+        assert_eq!(tracks[0].number_of_successful_updates, 4);
+    }
+
+    /// 50x50 box moving right by `speed` pixels per frame.
+    fn moving_box(frame: i32, speed: f64) -> [f64; 4] {
+        let x = 100.0 + speed * frame as f64;
+        [x, 100.0, x + 50.0, 150.0]
+    }
+
+    /// Box the tracker's first track predicts for the next frame.
+    fn next_prediction(tracker: &OcSortTracker<f64>) -> [f64; 4] {
+        let mut kalman = tracker.tracks[0].kalman.clone();
+        kalman.predict();
+        kalman.get_state_bbox()
+    }
+
+    #[test]
+    fn reupdate_replays_the_interpolated_trajectory() {
+        let config = OcSortConfig::default();
+        let delta_t = config.delta_t;
+        let mut track = OcSortTrack::new(&moving_box(0, 10.0), 0, &config);
+        for frame in 1..4 {
+            track.predict(delta_t);
+            track
+                .update(&moving_box(frame, 10.0), 0.9, delta_t)
+                .unwrap();
+        }
+        // Missed at frames 4 to 6, the filter is frozen at the first miss.
+        let mut replay = None;
+        for _ in 4..7 {
+            track.predict(delta_t);
+            track.mark_missed();
+            replay.get_or_insert_with(|| track.kalman.clone());
+        }
+        track.predict(delta_t);
+        let mut dead_reckoned = track.kalman.clone();
+        track.update(&moving_box(7, 10.0), 0.9, delta_t).unwrap();
+
+        // The virtual boxes lie on the straight line to the new observation.
+        let mut replay = replay.unwrap();
+        for frame in 4..7 {
+            replay.correct(&moving_box(frame, 10.0), None).unwrap();
+            replay.predict();
+        }
+        replay
+            .update_with_score(&moving_box(7, 10.0), Some(0.9))
+            .unwrap();
+        dead_reckoned
+            .update_with_score(&moving_box(7, 10.0), Some(0.9))
+            .unwrap();
+
+        assert!(track.frozen.is_none());
+        assert_eq!(track.kalman.get_state_bbox(), replay.get_state_bbox());
+        assert_eq!(
+            track.kalman.innovation_covariance(),
+            replay.innovation_covariance()
+        );
+        assert_ne!(
+            track.kalman.get_state_bbox(),
+            dead_reckoned.get_state_bbox()
+        );
+    }
+
+    #[test]
+    fn momentum_prefers_detections_along_the_motion() {
+        let mut tracker = OcSortTracker::<f64>::new(OcSortConfig::default()).unwrap();
+        for frame in 0..5 {
+            tracker
+                .update(&[Detection::new(moving_box(frame, 10.0), 0, 0.9)])
+                .unwrap();
+        }
+
+        // Two detections overlapping the prediction equally, one shifted
+        // along the motion and one across it, listed first.
+        let [x1, y1, x2, y2] = next_prediction(&tracker);
+        let (dx, dy) = (0.2 * (x2 - x1), 0.2 * (y2 - y1));
+        let across = Detection::new([x1, y1 + dy, x2, y2 + dy], 0, 0.9);
+        let along = Detection::new([x1 + dx, y1, x2 + dx, y2], 0, 0.9);
+        let output = tracker.update(&[across, along]).unwrap();
+
+        assert!((tracker.iou_matrix[0] - tracker.iou_matrix[1]).abs() < 1e-9);
+        assert!(tracker.cost_matrix[1] < tracker.cost_matrix[0]);
+        assert_eq!(output[0].tracker_id, -1);
+        assert_eq!(output[1].tracker_id, 0);
+    }
+
+    #[test]
+    fn recovery_matches_the_last_observation() {
+        let mut tracker = OcSortTracker::<f64>::new(OcSortConfig::default()).unwrap();
+        for frame in 0..8 {
+            tracker
+                .update(&[Detection::new(moving_box(frame, 20.0), 0, 0.9)])
+                .unwrap();
+        }
+        for _ in 0..2 {
+            tracker.update(&[]).unwrap();
+        }
+
+        // The object stopped where it was last seen, far behind the
+        // prediction.
+        let stopped = moving_box(7, 20.0);
+        let prediction = next_prediction(&tracker);
+        assert!(compute_iou(&prediction, &stopped) < 0.3);
+        let output = tracker.update(&[Detection::new(stopped, 0, 0.9)]).unwrap();
+
+        assert_eq!(output[0].tracker_id, 0);
+        assert_eq!(output[0].predicted_box, Some(prediction));
+        assert_eq!(tracker.tracks.len(), 1);
+    }
+}
@@ -0,0 +1,365 @@
+use nalgebra::DMatrix;
+
+use crate::float::Float;
+use crate::iou::compute_iou;
+
+/// Boxes as structure of arrays, so that a query box is compared with
+/// consecutive boxes lane by lane. Kept between frames to reuse its buffers.
+#[derive(Debug, Clone, Default)]
+pub struct BoxColumns<F> {
+    x1: Vec<F>,
+    y1: Vec<F>,
+    x2: Vec<F>,
+    y2: Vec<F>,
+    area: Vec<F>,
+}
+
+impl<F: Float> BoxColumns<F> {
+    pub(crate) fn fill(&mut self, boxes: &[[F; 4]]) {
+        self.x1.clear();
+        self.y1.clear();
+        self.x2.clear();
+        self.y2.clear();
+        self.area.clear();
+        for b in boxes {
+            self.x1.push(b[0]);
+            self.y1.push(b[1]);
+            self.x2.push(b[2]);
+            self.y2.push(b[3]);
+            self.area.push((b[2] - b[0]) * (b[3] - b[1]));
+        }
+    }
+}
+
+/// IoU matrix filled one detection column at a time, comparing each
+/// detection with every predicted box.
+pub(crate) fn iou_batch_by_column<F: Float>(
+    predicted_boxes: &[[F; 4]],
+    detection_boxes: &[[F; 4]],
+) -> DMatrix<F> {
+    let n_trackers = predicted_boxes.len();
+    let mut iou_matrix = DMatrix::zeros(n_trackers, detection_boxes.len());
+    if iou_matrix.is_empty() {
+        return iou_matrix;
+    }
+
+    // Column-major, so each detection's column is contiguous.
+    let mut columns = BoxColumns::default();
+    columns.fill(predicted_boxes);
+    for (det_box, out) in detection_boxes
+        .iter()
+        .zip(iou_matrix.as_mut_slice().chunks_exact_mut(n_trackers))
+    {
+        F::iou_against(&columns, det_box, out, KernelToken(()));
+    }
+
+    iou_matrix
+}
+
+/// Row-major IoU of every predicted box (rows) with the detections in
+/// `detection_columns` (columns), written to `out` without allocating.
+pub(crate) fn iou_rows_into<F: Float>(
+    predicted_boxes: &[[F; 4]],
+    detection_columns: &BoxColumns<F>,
+    out: &mut Vec<F>,
+) {
+    let n_detections = detection_columns.x1.len();
+    out.clear();
+    out.resize(predicted_boxes.len() * n_detections, F::zero());
+    if n_detections == 0 {
+        return;
+    }
+    for (pred_box, row) in predicted_boxes
+        .iter()
+        .zip(out.chunks_exact_mut(n_detections))
+    {
+        F::iou_against(detection_columns, pred_box, row, KernelToken(()));
+    }
+}
+
+/// Scalar kernel, also finishing the lanes left over by the vector kernels.
+/// IoU is symmetric down to the last bit, so `columns` may hold either side.
+fn iou_against_scalar<F: Float>(columns: &BoxColumns<F>, query: &[F; 4], out: &mut [F]) {
+    iou_against_scalar_from(columns, query, out, 0);
+}
+
+fn iou_against_scalar_from<F: Float>(
+    columns: &BoxColumns<F>,
+    query: &[F; 4],
+    out: &mut [F],
+    start: usize,
+) {
+    for (k, iou) in out.iter_mut().enumerate().skip(start) {
+        let column_box = [columns.x1[k], columns.y1[k], columns.x2[k], columns.y2[k]];
+        *iou = compute_iou(&column_box, query);
+    }
+}
+
+/// Argument only this module can construct, so that the kernel cannot be
+/// called through a `Float` bound outside the crate.
+pub struct KernelToken(());
+
+/// IoU kernel of each float type, behind [`compute_iou_batch`](crate::compute_iou_batch).
+///
+/// A supertrait of [`Float`] that is public but unnameable outside the crate,
+/// which keeps [`BoxColumns`] out of the public API and `Float` sealed.
+pub trait IouKernel: Sized {
+    /// IoU of `query` with every box in `columns`.
+    ///
+    /// # Panics
+    ///
+    /// If `out` and `columns` differ in length.
+    fn iou_against(
+        columns: &BoxColumns<Self>,
+        query: &[Self; 4],
+        out: &mut [Self],
+        token: KernelToken,
+    );
+}
+
+impl IouKernel for f32 {
+    fn iou_against(
+        columns: &BoxColumns<Self>,
+        query: &[Self; 4],
+        out: &mut [Self],
+        _: KernelToken,
+    ) {
+        iou_against_f32(columns, query, out)
+    }
+}
+
+impl IouKernel for f64 {
+    fn iou_against(
+        columns: &BoxColumns<Self>,
+        query: &[Self; 4],
+        out: &mut [Self],
+        _: KernelToken,
+    ) {
+        iou_against_f64(columns, query, out)
+    }
+}
+
+fn iou_against_f32(columns: &BoxColumns<f32>, query: &[f32; 4], out: &mut [f32]) {
+    // The vector kernels read every column at each index of `out`.
+    assert_eq!(out.len(), columns.x1.len());
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx") {
+        // SAFETY: AVX support was detected above, and `out` is no longer
+        // than the columns.
+        return unsafe { avx::iou_against_f32(columns, query, out) };
+    }
+    iou_against_scalar(columns, query, out)
+}
+
+fn iou_against_f64(columns: &BoxColumns<f64>, query: &[f64; 4], out: &mut [f64]) {
+    assert_eq!(out.len(), columns.x1.len());
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx") {
+        // SAFETY: as for `iou_against_f32`.
+        return unsafe { avx::iou_against_f64(columns, query, out) };
+    }
+    iou_against_scalar(columns, query, out)
+}
+
+/// AVX kernels performing the operations of [`compute_iou`] in the same
+/// order, so results are bit-identical to the scalar path for finite boxes.
+#[cfg(target_arch = "x86_64")]
+mod avx {
+    use std::arch::x86_64::*;
+
+    use super::{iou_against_scalar_from, BoxColumns};
+
+    /// # Safety
+    ///
+    /// The CPU must support AVX and `out` must be no longer than `columns`.
+    #[target_feature(enable = "avx")]
+    pub(super) unsafe fn iou_against_f32(
+        columns: &BoxColumns<f32>,
+        query: &[f32; 4],
+        out: &mut [f32],
+    ) {
+        const LANES: usize = 8;
+        let n = out.len();
+        let query_x1 = _mm256_set1_ps(query[0]);
+        let query_y1 = _mm256_set1_ps(query[1]);
+        let query_x2 = _mm256_set1_ps(query[2]);
+        let query_y2 = _mm256_set1_ps(query[3]);
+        let query_area = _mm256_set1_ps((query[2] - query[0]) * (query[3] - query[1]));
+        let zero = _mm256_setzero_ps();
+
+        let mut k = 0;
+        while k + LANES <= n {
+            let x1 = _mm256_max_ps(_mm256_loadu_ps(columns.x1.as_ptr().add(k)), query_x1);
+            let y1 = _mm256_max_ps(_mm256_loadu_ps(columns.y1.as_ptr().add(k)), query_y1);
+            let x2 = _mm256_min_ps(_mm256_loadu_ps(columns.x2.as_ptr().add(k)), query_x2);
+            let y2 = _mm256_min_ps(_mm256_loadu_ps(columns.y2.as_ptr().add(k)), query_y2);
+            // Not-less-or-equal keeps NaN lanes like the scalar comparison.
+            let overlaps = _mm256_and_ps(
+                _mm256_cmp_ps::<_CMP_NLE_UQ>(x2, x1),
+                _mm256_cmp_ps::<_CMP_NLE_UQ>(y2, y1),
+            );
+
+            let inter = _mm256_mul_ps(_mm256_sub_ps(x2, x1), _mm256_sub_ps(y2, y1));
+            let column_area = _mm256_loadu_ps(columns.area.as_ptr().add(k));
+            let union = _mm256_sub_ps(_mm256_add_ps(column_area, query_area), inter);
+            let valid = _mm256_and_ps(overlaps, _mm256_cmp_ps::<_CMP_NLE_UQ>(union, zero));
+
+            let iou = _mm256_and_ps(valid, _mm256_div_ps(inter, union));
+            _mm256_storeu_ps(out.as_mut_ptr().add(k), iou);
+            k += LANES;
+        }
+        iou_against_scalar_from(columns, query, out, k);
+    }
+
+    /// # Safety
+    ///
+    /// The CPU must support AVX and `out` must be no longer than `columns`.
+    #[target_feature(enable = "avx")]
+    pub(super) unsafe fn iou_against_f64(
+        columns: &BoxColumns<f64>,
+        query: &[f64; 4],
+        out: &mut [f64],
+    ) {
+        const LANES: usize = 4;
+        let n = out.len();
+        let query_x1 = _mm256_set1_pd(query[0]);
+        let query_y1 = _mm256_set1_pd(query[1]);
+        let query_x2 = _mm256_set1_pd(query[2]);
+        let query_y2 = _mm256_set1_pd(query[3]);
+        let query_area = _mm256_set1_pd((query[2] - query[0]) * (query[3] - query[1]));
+        let zero = _mm256_setzero_pd();
+
+        let mut k = 0;
+        while k + LANES <= n {
+            let x1 = _mm256_max_pd(_mm256_loadu_pd(columns.x1.as_ptr().add(k)), query_x1);
+            let y1 = _mm256_max_pd(_mm256_loadu_pd(columns.y1.as_ptr().add(k)), query_y1);
+            let x2 = _mm256_min_pd(_mm256_loadu_pd(columns.x2.as_ptr().add(k)), query_x2);
+            let y2 = _mm256_min_pd(_mm256_loadu_pd(columns.y2.as_ptr().add(k)), query_y2);
+            let overlaps = _mm256_and_pd(
+                _mm256_cmp_pd::<_CMP_NLE_UQ>(x2, x1),
+                _mm256_cmp_pd::<_CMP_NLE_UQ>(y2, y1),
+            );
+
+            let inter = _mm256_mul_pd(_mm256_sub_pd(x2, x1), _mm256_sub_pd(y2, y1));
+            let column_area = _mm256_loadu_pd(columns.area.as_ptr().add(k));
+            let union = _mm256_sub_pd(_mm256_add_pd(column_area, query_area), inter);
+            let valid = _mm256_and_pd(overlaps, _mm256_cmp_pd::<_CMP_NLE_UQ>(union, zero));
+
+            let iou = _mm256_and_pd(valid, _mm256_div_pd(inter, union));
+            _mm256_storeu_pd(out.as_mut_ptr().add(k), iou);
+            k += LANES;
+        }
+        iou_against_scalar_from(columns, query, out, k);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Random boxes plus degenerate ones: zero width, zero height, a point,
+    /// inverted corners, and boxes touching, containing or equal to others.
+    fn test_boxes() -> Vec<[f32; 4]> {
+        let mut boxes = vec![
+            [10.0, 10.0, 10.0, 50.0],
+            [10.0, 10.0, 50.0, 10.0],
+            [30.0, 30.0, 30.0, 30.0],
+            [50.0, 50.0, 10.0, 10.0],
+            [0.0, 0.0, 40.0, 40.0],
+            [40.0, 0.0, 80.0, 40.0],
+            [10.0, 10.0, 20.0, 20.0],
+            [0.0, 0.0, 40.0, 40.0],
+        ];
+        let mut state = 12345u64;
+        let mut next = || {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 40) as f32 / (1u64 << 24) as f32 * 100.0
+        };
+        for _ in 0..21 {
+            let (x, y) = (next(), next());
+            boxes.push([x, y, x + next() / 2.0, y + next() / 2.0]);
+        }
+        boxes
+    }
+
+    /// Compares the dispatched kernel, vectorized where the CPU allows, with
+    /// the scalar one for every prefix of `boxes`, so every tail length after
+    /// the full vectors is covered.
+    fn assert_kernel_matches_scalar<F: Float>() {
+        let boxes: Vec<[F; 4]> = test_boxes().iter().map(|b| b.map(F::of_f32)).collect();
+        assert_eq!(boxes.len() % 8, 5);
+
+        let mut columns = BoxColumns::default();
+        for len in 0..=boxes.len() {
+            columns.fill(&boxes[..len]);
+            for query in &boxes {
+                let mut kernel = vec![F::zero(); len];
+                let mut scalar = vec![F::zero(); len];
+                F::iou_against(&columns, query, &mut kernel, KernelToken(()));
+                iou_against_scalar(&columns, query, &mut scalar);
+                for (k, (a, b)) in kernel.iter().zip(&scalar).enumerate() {
+                    assert_eq!(
+                        a.as_f64().to_bits(),
+                        b.as_f64().to_bits(),
+                        "{:?} against {:?}: {} != {}",
+                        boxes[k],
+                        query,
+                        a,
+                        b
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn f32_kernel_matches_scalar() {
+        assert_kernel_matches_scalar::<f32>();
+    }
+
+    #[test]
+    fn f64_kernel_matches_scalar() {
+        assert_kernel_matches_scalar::<f64>();
+    }
+
+    #[test]
+    #[should_panic]
+    fn f32_kernel_rejects_output_longer_than_columns() {
+        let mut out = vec![0.0; 64];
+        f32::iou_against(
+            &BoxColumns::default(),
+            &[0.0, 0.0, 1.0, 1.0],
+            &mut out,
+            KernelToken(()),
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn f64_kernel_rejects_output_shorter_than_columns() {
+        let mut columns = BoxColumns::default();
+        columns.fill(&[[0.0, 0.0, 1.0, 1.0]; 9]);
+        let mut out = vec![0.0; 8];
+        f64::iou_against(&columns, &[0.0, 0.0, 1.0, 1.0], &mut out, KernelToken(()));
+    }
+
+    #[test]
+    fn batch_matches_compute_iou() {
+        let boxes = test_boxes();
+        let (predicted, detections) = boxes.split_at(11);
+        let matrix = iou_batch_by_column(predicted, detections);
+        let mut rows = Vec::new();
+        let mut columns = BoxColumns::default();
+        columns.fill(detections);
+        iou_rows_into(predicted, &columns, &mut rows);
+        for (i, p) in predicted.iter().enumerate() {
+            for (j, d) in detections.iter().enumerate() {
+                let expected = compute_iou(p, d).to_bits();
+                assert_eq!(matrix[(i, j)].to_bits(), expected);
+                assert_eq!(rows[i * detections.len() + j].to_bits(), expected);
+            }
+        }
+    }
+}
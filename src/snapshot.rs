@@ -0,0 +1,84 @@
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::config::ByteTrackConfig;
+use crate::events::TrackEvent;
+use crate::float::Float;
+use crate::kalman::TrackSnapshot;
+
+/// Format version of the snapshots written by this crate. Bumped whenever
+/// the serialized layout changes.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Complete state of a [`ByteTrackTracker`](crate::ByteTrackTracker):
+/// config, ID counter, frame count and every track with its Kalman state
+/// and covariance, taken by
+/// [`snapshot`](crate::ByteTrackTracker::snapshot) and turned back into a
+/// tracker by [`restore`](crate::ByteTrackTracker::restore).
+///
+/// Serialize it with any serde format to persist tracks across restarts.
+/// Deserializing a snapshot of another [`SNAPSHOT_VERSION`] fails instead
+/// of restoring a tracker from a misread state.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ByteTrackSnapshot<F: Float = f32> {
+    version: Version,
+    pub(crate) config: ByteTrackConfig,
+    pub(crate) next_tracker_id: i32,
+    pub(crate) frame_count: u64,
+    pub(crate) tracks: Vec<TrackSnapshot<F>>,
+    pub(crate) removed_tracks: Vec<TrackSnapshot<F>>,
+    pub(crate) events: Vec<TrackEvent<F>>,
+}
+
+impl<F: Float> ByteTrackSnapshot<F> {
+    pub(crate) fn new(
+        config: ByteTrackConfig,
+        next_tracker_id: i32,
+        frame_count: u64,
+        tracks: Vec<TrackSnapshot<F>>,
+        removed_tracks: Vec<TrackSnapshot<F>>,
+        events: Vec<TrackEvent<F>>,
+    ) -> Self {
+        Self {
+            version: Version(SNAPSHOT_VERSION),
+            config,
+            next_tracker_id,
+            frame_count,
+            tracks,
+            removed_tracks,
+            events,
+        }
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version.0
+    }
+
+    pub fn config(&self) -> &ByteTrackConfig {
+        &self.config
+    }
+
+    /// Number of frames processed before the snapshot was taken.
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+}
+
+/// Snapshot version, checked before the fields after it are read.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(transparent)]
+struct Version(u32);
+
+impl<'de> Deserialize<'de> for Version {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let version = u32::deserialize(deserializer)?;
+        if version != SNAPSHOT_VERSION {
+            return Err(D::Error::custom(format!(
+                "unsupported snapshot version {}, expected {}",
+                version, SNAPSHOT_VERSION
+            )));
+        }
+        Ok(Self(version))
+    }
+}
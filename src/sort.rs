@@ -0,0 +1,316 @@
+use crate::config::SortConfig;
+use crate::detection::{check_detections, Detection, TrackedDetection};
+use crate::error::Result;
+use crate::events::{record_events, TrackEvent};
+use crate::float::Float;
+use crate::iou::{compute_iou, IouMetric};
+use crate::kalman::KalmanBoxTracker;
+use crate::tracker::{active_tracks, lost_tracks, TrackInfo, Tracker, TrackerConfig};
+use crate::utils::{is_alive, prune_tracks, BoxAssociation};
+
+pub struct SortTracker<F: Float = f32> {
+    config: SortConfig,
+    maximum_frames_without_update: i32,
+    next_tracker_id: i32,
+    frame_count: u64,
+
+    // Buffers for memory reuse
+    tracks: Vec<KalmanBoxTracker<F>>,
+    /// Tracks at the start of the frame, restored when it fails.
+    saved_tracks: Vec<KalmanBoxTracker<F>>,
+    detection_boxes: Vec<[F; 4]>,
+    predicted_boxes: Vec<[F; 4]>,
+    final_boxes: Vec<[F; 4]>,
+    track_predictions: Vec<Option<[F; 4]>>,
+    candidate_pairs: Vec<(usize, usize, F)>,
+    used_tracks: Vec<bool>,
+    used_detections: Vec<bool>,
+    removed_tracks: Vec<KalmanBoxTracker<F>>,
+    events: Vec<TrackEvent<F>>,
+}
+
+impl<F: Float> SortTracker<F> {
+    /// Creates a tracker after validating `config`.
+    pub fn new(config: SortConfig) -> Result<Self> {
+        config.validate()?;
+
+        Ok(Self {
+            maximum_frames_without_update: config.maximum_frames_without_update(),
+            config,
+            next_tracker_id: 0,
+            frame_count: 0,
+            tracks: Vec::new(),
+            saved_tracks: Vec::new(),
+            detection_boxes: Vec::new(),
+            predicted_boxes: Vec::new(),
+            final_boxes: Vec::new(),
+            track_predictions: Vec::new(),
+            candidate_pairs: Vec::new(),
+            used_tracks: Vec::new(),
+            used_detections: Vec::new(),
+            removed_tracks: Vec::new(),
+            events: Vec::new(),
+        })
+    }
+
+    pub fn config(&self) -> &SortConfig {
+        &self.config
+    }
+
+    fn spawn_new_trackers(&mut self, detections: &[Detection<F>], unmatched_detections: &[usize]) {
+        for &det_idx in unmatched_detections {
+            let detection = &detections[det_idx];
+            if detection.score >= F::of_f64(self.config.track_activation_threshold) {
+                self.tracks.push(KalmanBoxTracker::with_noise(
+                    &detection.box_coords,
+                    detection.class_id,
+                    self.config.motion_model,
+                    self.config.noise,
+                ));
+            }
+        }
+    }
+
+    /// Assigns IDs to detections by greedily pairing them with the updated
+    /// tracks in descending IoU order, as `update_detections_with_track_ids`
+    /// does in the Python implementation.
+    fn assign_track_ids(&mut self, detections: &[Detection<F>]) -> Vec<TrackedDetection<F>> {
+        let mut tracked: Vec<TrackedDetection<F>> = detections
+            .iter()
+            .enumerate()
+            .map(|(idx, det)| TrackedDetection::new(det, idx, -1, None))
+            .collect();
+
+        self.final_boxes.clear();
+        for t in &self.tracks {
+            self.final_boxes.push(t.get_state_bbox());
+        }
+
+        self.candidate_pairs.clear();
+        for (row, det_box) in self.detection_boxes.iter().enumerate() {
+            for (col, track_box) in self.final_boxes.iter().enumerate() {
+                let iou = compute_iou(det_box, track_box);
+                if iou > F::of_f64(self.config.minimum_iou_threshold) {
+                    self.candidate_pairs.push((row, col, iou));
+                }
+            }
+        }
+
+        // Stable sort keeps row-major order for equal IoU, like Python's `sorted`.
+        self.candidate_pairs
+            .sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap_or(std::cmp::Ordering::Equal));
+
+        self.used_tracks.clear();
+        self.used_tracks.resize(self.tracks.len(), false);
+        self.used_detections.clear();
+        self.used_detections.resize(detections.len(), false);
+
+        for &(row, col, _) in &self.candidate_pairs {
+            if self.used_detections[row] || self.used_tracks[col] {
+                continue;
+            }
+            self.used_detections[row] = true;
+            self.used_tracks[col] = true;
+
+            let track = &mut self.tracks[col];
+            if track.number_of_successful_updates >= self.config.minimum_consecutive_frames {
+                if track.tracker_id == -1 {
+                    track.confirm(self.next_tracker_id);
+                    self.next_tracker_id += 1;
+                }
+                tracked[row].tracker_id = track.tracker_id;
+                tracked[row].predicted_box = self.track_predictions[col];
+            }
+        }
+
+        tracked
+    }
+
+    /// Advances the tracker by one frame. Errors are described on
+    /// [`Tracker::update`].
+    pub fn update(&mut self, detections: &[Detection<F>]) -> Result<Vec<TrackedDetection<F>>> {
+        check_detections(detections, self.config.motion_model)?;
+
+        let frame_index = self.frame_count;
+        if self.tracks.is_empty() && detections.is_empty() {
+            self.frame_count += 1;
+            self.events.clear();
+            self.removed_tracks.clear();
+            return Ok(Vec::new());
+        }
+
+        // A singular covariance fails after the tracks have changed, the
+        // saved copy restores them.
+        self.saved_tracks.clone_from(&self.tracks);
+        if let Err(error) = self.match_detections(detections) {
+            std::mem::swap(&mut self.tracks, &mut self.saved_tracks);
+            return Err(error);
+        }
+
+        // Spawned tracks have no prediction for this frame.
+        self.track_predictions.clear();
+        for (i, track) in self.tracks.iter().enumerate() {
+            if is_alive(
+                track,
+                self.config.minimum_consecutive_frames,
+                self.maximum_frames_without_update,
+            ) {
+                self.track_predictions
+                    .push(self.predicted_boxes.get(i).copied());
+            }
+        }
+
+        self.frame_count += 1;
+        self.events.clear();
+        prune_tracks(
+            &mut self.tracks,
+            |track| track,
+            self.config.minimum_consecutive_frames,
+            self.maximum_frames_without_update,
+            &mut self.removed_tracks,
+        );
+
+        let tracked = self.assign_track_ids(detections);
+        record_events(
+            frame_index,
+            &mut self.tracks,
+            &self.removed_tracks,
+            &mut self.events,
+        );
+
+        Ok(tracked)
+    }
+
+    /// Predicts the tracks, updates those matched to `detections` and spawns
+    /// tracks for the unmatched ones.
+    fn match_detections(&mut self, detections: &[Detection<F>]) -> Result<()> {
+        self.detection_boxes.clear();
+        for d in detections {
+            self.detection_boxes.push(d.box_coords);
+        }
+
+        for tracker in &mut self.tracks {
+            tracker.predict();
+        }
+
+        self.predicted_boxes.clear();
+        for t in &self.tracks {
+            self.predicted_boxes.push(t.get_state_bbox());
+        }
+
+        let association = BoxAssociation {
+            metric: IouMetric::Iou,
+            solver: self.config.solver,
+            spatial_index: self.config.spatial_index,
+        };
+        let (matched, _, unmatched_dets) = association.associate(
+            &self.predicted_boxes,
+            &self.detection_boxes,
+            F::of_f64(self.config.minimum_iou_threshold),
+        );
+
+        for &(track_idx, det_idx) in &matched {
+            self.tracks[track_idx].update_with_score(
+                &self.detection_boxes[det_idx],
+                Some(detections[det_idx].score),
+            )?;
+        }
+
+        self.spawn_new_trackers(detections, &unmatched_dets);
+
+        Ok(())
+    }
+}
+
+impl<F: Float> Tracker<F> for SortTracker<F> {
+    fn name(&self) -> &'static str {
+        "SORT"
+    }
+
+    fn update(&mut self, detections: &[Detection<F>]) -> Result<Vec<TrackedDetection<F>>> {
+        SortTracker::update(self, detections)
+    }
+
+    fn reset(&mut self) {
+        self.tracks.clear();
+        self.removed_tracks.clear();
+        self.events.clear();
+        self.next_tracker_id = 0;
+        self.frame_count = 0;
+    }
+
+    fn active_tracks(&self) -> Vec<TrackInfo<F>> {
+        active_tracks(&self.tracks)
+    }
+
+    fn lost_tracks(&self) -> Vec<TrackInfo<F>> {
+        lost_tracks(&self.tracks)
+    }
+
+    fn removed_tracks(&self) -> Vec<TrackInfo<F>> {
+        self.removed_tracks.iter().map(TrackInfo::from).collect()
+    }
+
+    fn events(&self) -> &[TrackEvent<F>] {
+        &self.events
+    }
+
+    fn config(&self) -> TrackerConfig {
+        TrackerConfig::Sort(self.config.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kalman::TrackState;
+
+    fn ids(tracker: &mut SortTracker<f64>, detections: &[Detection<f64>]) -> Vec<i32> {
+        tracker
+            .update(detections)
+            .unwrap()
+            .iter()
+            .map(|d| d.tracker_id)
+            .collect()
+    }
+
+    #[test]
+    fn ids_wait_for_minimum_hits_and_tracks_expire_after_maximum_age() {
+        let config = SortConfig::default().with_lost_track_buffer(2);
+        let mut tracker = SortTracker::<f64>::new(config).unwrap();
+        let still = Detection::new([100.0, 100.0, 150.0, 200.0], 0, 0.9);
+        let walking = |frame: usize| {
+            let x = 300.0 + 5.0 * frame as f64;
+            Detection::new([x, 100.0, x + 50.0, 200.0], 0, 0.9)
+        };
+
+        // `minimum_consecutive_frames` is 3, IDs follow confirmation order.
+        for frame in 0..2 {
+            assert_eq!(
+                ids(&mut tracker, &[still.clone(), walking(frame)]),
+                [-1, -1]
+            );
+        }
+        assert_eq!(ids(&mut tracker, &[still.clone(), walking(2)]), [0, 1]);
+        assert_eq!(ids(&mut tracker, &[walking(3), still.clone()]), [1, 0]);
+
+        // With `maximum_frames_without_update` of 2 the walker survives one
+        // missed frame and is removed at the second.
+        assert_eq!(ids(&mut tracker, std::slice::from_ref(&still)), [0]);
+        assert_eq!(tracker.lost_tracks()[0].tracker_id, 1);
+        assert_eq!(ids(&mut tracker, std::slice::from_ref(&still)), [0]);
+        let removed = tracker.removed_tracks();
+        assert_eq!(removed.len(), 1);
+        assert_eq!(
+            (removed[0].tracker_id, removed[0].state),
+            (1, TrackState::Removed)
+        );
+
+        // Coming back spawns a new track, which gets the next ID.
+        for frame in 6..8 {
+            assert_eq!(ids(&mut tracker, &[still.clone(), walking(frame)]), [0, -1]);
+        }
+        assert_eq!(ids(&mut tracker, &[still.clone(), walking(8)]), [0, 2]);
+        assert_eq!(tracker.active_tracks().len(), 2);
+    }
+}
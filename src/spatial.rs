@@ -0,0 +1,229 @@
+use std::ops::RangeInclusive;
+
+use nalgebra::DMatrix;
+
+use crate::float::Float;
+
+/// Similarity of the track-detection pairs whose boxes can overlap, all other
+/// pairs being zero. Rows are tracks and columns detections, as in the dense
+/// matrix of [`compute_iou_batch`](crate::compute_iou_batch).
+#[derive(Debug, Clone, PartialEq)]
+pub struct SparseSimilarity<F: Float = f32> {
+    n_rows: usize,
+    n_cols: usize,
+    /// `(row, col, similarity)` sorted by row, then column.
+    entries: Vec<(usize, usize, F)>,
+}
+
+impl<F: Float> SparseSimilarity<F> {
+    pub fn nrows(&self) -> usize {
+        self.n_rows
+    }
+
+    pub fn ncols(&self) -> usize {
+        self.n_cols
+    }
+
+    /// Stored `(row, col, similarity)` entries sorted by row, then column.
+    pub fn entries(&self) -> &[(usize, usize, F)] {
+        &self.entries
+    }
+
+    /// Similarity of track `row` and detection `col`.
+    pub fn get(&self, row: usize, col: usize) -> F {
+        self.entries
+            .binary_search_by(|&(r, c, _)| (r, c).cmp(&(row, col)))
+            .map_or(F::zero(), |k| self.entries[k].2)
+    }
+
+    pub fn to_dense(&self) -> DMatrix<F> {
+        let mut dense = DMatrix::zeros(self.n_rows, self.n_cols);
+        for &(row, col, value) in &self.entries {
+            dense[(row, col)] = value;
+        }
+        dense
+    }
+
+    /// Multiplies every column by the score of its detection, as BoT-SORT
+    /// fuses scores into IoU.
+    pub(crate) fn scale_columns(&mut self, scores: &[F]) {
+        for (_, col, value) in &mut self.entries {
+            *value *= scores[*col];
+        }
+    }
+
+    /// Evaluates `similarity` for every pair of `predicted_boxes` and
+    /// `detection_boxes` whose `reach` boxes overlap. `similarity` must be
+    /// zero for pairs whose `reach` boxes are disjoint.
+    pub(crate) fn from_overlaps(
+        predicted_boxes: &[[F; 4]],
+        detection_boxes: &[[F; 4]],
+        reach: impl Fn(&[F; 4]) -> [F; 4],
+        similarity: impl Fn(&[F; 4], &[F; 4]) -> F,
+    ) -> Self {
+        let predicted_reach: Vec<[F; 4]> = predicted_boxes.iter().map(&reach).collect();
+        let grid = BoxGrid::new(&predicted_reach);
+
+        let mut entries = Vec::new();
+        let mut candidates = Vec::new();
+        let mut last_query = vec![usize::MAX; predicted_boxes.len()];
+        for (col, det_box) in detection_boxes.iter().enumerate() {
+            candidates.clear();
+            grid.query(&reach(det_box), col, &mut last_query, &mut candidates);
+            for &row in &candidates {
+                let value = similarity(&predicted_boxes[row], det_box);
+                if value != F::zero() {
+                    entries.push((row, col, value));
+                }
+            }
+        }
+        entries.sort_unstable_by_key(|&(row, col, _)| (row, col));
+
+        Self {
+            n_rows: predicted_boxes.len(),
+            n_cols: detection_boxes.len(),
+            entries,
+        }
+    }
+}
+
+/// IoU of the predicted and detection boxes that overlap, found through a
+/// uniform grid over the predicted boxes instead of comparing every pair.
+/// Densifies to [`compute_iou_batch`](crate::compute_iou_batch).
+pub fn compute_iou_sparse<F: Float>(
+    predicted_boxes: &[[F; 4]],
+    detection_boxes: &[[F; 4]],
+) -> SparseSimilarity<F> {
+    SparseSimilarity::from_overlaps(
+        predicted_boxes,
+        detection_boxes,
+        |bbox| *bbox,
+        crate::iou::compute_iou,
+    )
+}
+
+/// Uniform grid bucketing boxes by the cells they cover. A query lists the
+/// boxes sharing a cell with the query box, a superset of those overlapping
+/// it.
+struct BoxGrid<F: Float> {
+    origin: [F; 2],
+    cell_size: [F; 2],
+    dims: [usize; 2],
+    /// Boxes of cell `k` are `items[cell_start[k]..cell_start[k + 1]]`.
+    cell_start: Vec<usize>,
+    items: Vec<usize>,
+}
+
+impl<F: Float> BoxGrid<F> {
+    /// Cells are about the mean box size, capped at a few per box.
+    fn new(boxes: &[[F; 4]]) -> Self {
+        let mut min = [F::max_value().unwrap(); 2];
+        let mut max = [F::min_value().unwrap(); 2];
+        let mut size_sum = [F::zero(); 2];
+        let mut n_valid = 0usize;
+        for bbox in boxes.iter().filter(|b| has_area(b)) {
+            for axis in 0..2 {
+                min[axis] = min[axis].min(bbox[axis]);
+                max[axis] = max[axis].max(bbox[axis + 2]);
+                size_sum[axis] += bbox[axis + 2] - bbox[axis];
+            }
+            n_valid += 1;
+        }
+
+        let max_dim = 2 * (n_valid as f64).sqrt().ceil() as usize + 1;
+        let mut cell_size = [F::one(); 2];
+        let mut dims = [1usize; 2];
+        if n_valid > 0 {
+            for axis in 0..2 {
+                let extent = max[axis] - min[axis];
+                let mean_size = size_sum[axis] / F::of_f64(n_valid as f64);
+                let cells = (extent / mean_size).as_f64().ceil();
+                dims[axis] = (cells as usize).clamp(1, max_dim);
+                cell_size[axis] = extent / F::of_f64(dims[axis] as f64);
+            }
+        }
+
+        let mut grid = Self {
+            origin: min,
+            cell_size,
+            dims,
+            cell_start: vec![0; dims[0] * dims[1] + 1],
+            items: Vec::new(),
+        };
+
+        // Counting sort of the boxes into the cells they cover.
+        for bbox in boxes.iter().filter(|b| has_area(b)) {
+            let (xs, ys) = grid.cell_range(bbox);
+            for y in ys {
+                for x in xs.clone() {
+                    grid.cell_start[y * dims[0] + x + 1] += 1;
+                }
+            }
+        }
+        for k in 0..dims[0] * dims[1] {
+            grid.cell_start[k + 1] += grid.cell_start[k];
+        }
+        let mut next = grid.cell_start.clone();
+        grid.items = vec![0; grid.cell_start[dims[0] * dims[1]]];
+        for (index, bbox) in boxes.iter().enumerate().filter(|(_, b)| has_area(b)) {
+            let (xs, ys) = grid.cell_range(bbox);
+            for y in ys {
+                for x in xs.clone() {
+                    let cell = y * dims[0] + x;
+                    grid.items[next[cell]] = index;
+                    next[cell] += 1;
+                }
+            }
+        }
+
+        grid
+    }
+
+    /// Appends to `out` every box sharing a cell with `bbox`, once each.
+    /// `last_query` remembers per box the last `query_id` that listed it.
+    fn query(
+        &self,
+        bbox: &[F; 4],
+        query_id: usize,
+        last_query: &mut [usize],
+        out: &mut Vec<usize>,
+    ) {
+        if !has_area(bbox) || self.items.is_empty() {
+            return;
+        }
+        let (xs, ys) = self.cell_range(bbox);
+        for y in ys {
+            for x in xs.clone() {
+                let cell = y * self.dims[0] + x;
+                for &index in &self.items[self.cell_start[cell]..self.cell_start[cell + 1]] {
+                    if last_query[index] != query_id {
+                        last_query[index] = query_id;
+                        out.push(index);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Columns and rows of the cells covered by `bbox`, clamped to the grid.
+    fn cell_range(&self, bbox: &[F; 4]) -> (RangeInclusive<usize>, RangeInclusive<usize>) {
+        let cell = |value: F, axis: usize| {
+            let position = ((value - self.origin[axis]) / self.cell_size[axis]).as_f64();
+            // NaN from a zero cell size or outlying values land on the edges.
+            if position >= 0.0 {
+                (position as usize).min(self.dims[axis] - 1)
+            } else {
+                0
+            }
+        };
+        (
+            cell(bbox[0], 0)..=cell(bbox[2], 0),
+            cell(bbox[1], 1)..=cell(bbox[3], 1),
+        )
+    }
+}
+
+/// Boxes without a positive finite area overlap nothing.
+fn has_area<F: Float>(bbox: &[F; 4]) -> bool {
+    bbox.iter().all(|v| v.is_finite()) && bbox[2] > bbox[0] && bbox[3] > bbox[1]
+}
@@ -1,239 +1,240 @@
-use crate::detection::{Detection, TrackedDetection};
-use crate::iou::compute_iou_batch;
-use crate::kalman::KalmanBoxTracker;
-use crate::utils::{get_alive_trackers, get_associated_indices};
-
-pub struct ByteTrackTracker {
-    maximum_frames_without_update: i32,
-    minimum_consecutive_frames: i32,
-    minimum_iou_threshold: f32,
-    track_activation_threshold: f32,
-    high_conf_det_threshold: f32,
-    next_tracker_id: i32,
-
-    // Buffers for memory reuse
-    tracks: Vec<KalmanBoxTracker>,
-    updated_detections: Vec<TrackedDetection>,
-    high_conf_detections: Vec<Detection>,
-    low_conf_detections: Vec<Detection>,
-    high_conf_boxes: Vec<[f32; 4]>,
-    low_conf_boxes: Vec<[f32; 4]>,
-    predicted_boxes: Vec<[f32; 4]>,
-    remaining_predicted_boxes: Vec<[f32; 4]>,
-    matched_indices: Vec<(usize, usize)>,
-    matched_indices_adjusted: Vec<(usize, usize)>,
-    unmatched_track_indices: Vec<usize>,
-    unmatched_det_indices: Vec<usize>,
-    alive_indices: Vec<usize>,
-}
-
-impl ByteTrackTracker {
-    pub fn new(
-        lost_track_buffer: i32,
-        frame_rate: f32,
-        track_activation_threshold: f32,
-        minimum_consecutive_frames: i32,
-        minimum_iou_threshold: f32,
-        high_conf_det_threshold: f32,
-    ) -> Self {
-        let maximum_frames_without_update = (frame_rate / 30.0 * lost_track_buffer as f32) as i32;
+use serde::{Deserialize, Serialize};
 
-        Self {
-            maximum_frames_without_update,
-            minimum_consecutive_frames,
-            minimum_iou_threshold,
-            track_activation_threshold,
-            high_conf_det_threshold,
-            next_tracker_id: 0,
-            tracks: Vec::new(),
-            updated_detections: Vec::new(),
-            high_conf_detections: Vec::new(),
-            low_conf_detections: Vec::new(),
-            high_conf_boxes: Vec::new(),
-            low_conf_boxes: Vec::new(),
-            predicted_boxes: Vec::new(),
-            remaining_predicted_boxes: Vec::new(),
-            matched_indices: Vec::new(),
-            matched_indices_adjusted: Vec::new(),
-            unmatched_track_indices: Vec::new(),
-            unmatched_det_indices: Vec::new(),
-            alive_indices: Vec::new(),
-        }
+use crate::botsort::BotSortTracker;
+use crate::bytetrack::ByteTrackTracker;
+use crate::config::{BotSortConfig, ByteTrackConfig, OcSortConfig, SortConfig};
+use crate::detection::{Detection, TrackedDetection};
+use crate::error::Result;
+use crate::events::TrackEvent;
+use crate::float::Float;
+use crate::kalman::{KalmanBoxTracker, TrackState};
+use crate::ocsort::OcSortTracker;
+use crate::sort::SortTracker;
+
+/// Common interface of all tracking algorithms, so they can be swapped at
+/// runtime through `Box<dyn Tracker>`.
+///
+/// `F` is the floating-point precision of boxes, scores and filter state.
+/// Trackers are `Send` so that streams can be updated on different threads,
+/// see [`MultiStreamTracker`](crate::MultiStreamTracker).
+pub trait Tracker<F: Float = f32>: Send {
+    /// Short name of the algorithm, e.g. `"ByteTrack"`.
+    fn name(&self) -> &'static str;
+
+    /// Advances the tracker by one frame.
+    ///
+    /// An error leaves the tracker as it was before the call, so the stream
+    /// can continue with the next frame. A NaN or infinite score, or a box
+    /// without a finite Kalman measurement, returns
+    /// [`TrackerError::InvalidDetection`](crate::TrackerError::InvalidDetection).
+    /// Zero-width and zero-height boxes are accepted unless the motion model
+    /// cannot measure them. Errors found during association, such as a custom
+    /// association cost returning a matrix of the wrong shape or NaN
+    /// similarities, or a singular covariance, restore the tracks as they
+    /// were at the start of the frame.
+    fn update(&mut self, detections: &[Detection<F>]) -> Result<Vec<TrackedDetection<F>>>;
+
+    /// Same as [`update`](Self::update), writing the tracked detections to
+    /// `output` after clearing it. ByteTrack and BoT-SORT reuse their buffers
+    /// to avoid heap allocations, see
+    /// [`ByteTrackTracker::update_into`](crate::ByteTrackTracker::update_into).
+    fn update_into(
+        &mut self,
+        detections: &[Detection<F>],
+        output: &mut Vec<TrackedDetection<F>>,
+    ) -> Result<()> {
+        output.clear();
+        output.extend(self.update(detections)?);
+        Ok(())
     }
 
-    fn update_detections(
-        tracks: &mut [KalmanBoxTracker],
-        detections: &[Detection],
-        updated_detections: &mut Vec<TrackedDetection>,
-        matched_indices: &[(usize, usize)],
-        minimum_consecutive_frames: i32,
-        next_tracker_id: &mut i32,
-    ) {
-        for &(track_idx, det_idx) in matched_indices {
-            let bbox = &detections[det_idx].box_coords;
-            tracks[track_idx].update(bbox);
-
-            if tracks[track_idx].number_of_successful_updates >= minimum_consecutive_frames
-                && tracks[track_idx].tracker_id == -1
-            {
-                tracks[track_idx].tracker_id = *next_tracker_id;
-                *next_tracker_id += 1;
-            }
+    /// Drops all tracks and restarts ID numbering from zero.
+    fn reset(&mut self);
 
-            updated_detections.push(TrackedDetection {
-                box_coords: *bbox,
-                tracker_id: tracks[track_idx].tracker_id,
-            });
-        }
-    }
+    /// Tracks matched to a detection in the latest frame.
+    fn active_tracks(&self) -> Vec<TrackInfo<F>>;
 
-    fn split_detections(&mut self, detections: &[Detection]) {
-        self.high_conf_detections.clear();
-        self.low_conf_detections.clear();
+    /// Confirmed tracks kept alive without a match in the latest frame. They
+    /// keep their ID and are re-activated when matched again.
+    fn lost_tracks(&self) -> Vec<TrackInfo<F>>;
 
-        for det in detections {
-            if det.score >= self.high_conf_det_threshold {
-                self.high_conf_detections.push(det.clone());
-            } else {
-                self.low_conf_detections.push(det.clone());
-            }
-        }
-    }
+    /// Tracks dropped in the latest frame.
+    fn removed_tracks(&self) -> Vec<TrackInfo<F>>;
 
-    fn spawn_new_trackers(
-        &mut self,
-        detections: &[Detection],
-        detection_boxes: &[[f32; 4]],
-        unmatched_detections: &[usize],
-    ) {
-        for &det_idx in unmatched_detections {
-            if det_idx < detections.len() {
-                let confidence = detections[det_idx].score;
-                if confidence >= self.track_activation_threshold {
-                    let new_tracker = KalmanBoxTracker::new(&detection_boxes[det_idx]);
-                    self.tracks.push(new_tracker);
-
-                    self.updated_detections.push(TrackedDetection {
-                        box_coords: detection_boxes[det_idx],
-                        tracker_id: -1,
-                    });
-                }
-            }
-        }
-    }
+    /// Lifecycle events of the latest frame.
+    fn events(&self) -> &[TrackEvent<F>];
 
-    pub fn update(&mut self, detections: &[Detection]) -> Vec<TrackedDetection> {
-        if self.tracks.is_empty() && detections.is_empty() {
-            return Vec::new();
-        }
+    fn config(&self) -> TrackerConfig;
+}
 
-        self.updated_detections.clear();
+/// Snapshot of a single track, independent of the tracker implementation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrackInfo<F: Float = f32> {
+    /// `-1` until the track has enough consecutive updates.
+    pub tracker_id: i32,
+    pub class_id: i32,
+    pub state: TrackState,
+    /// Current state estimate as `[x1, y1, x2, y2]`.
+    pub box_coords: [F; 4],
+    pub time_since_update: i32,
+    pub number_of_successful_updates: i32,
+}
 
-        for tracker in &mut self.tracks {
-            tracker.predict();
+impl<F: Float> From<&KalmanBoxTracker<F>> for TrackInfo<F> {
+    fn from(track: &KalmanBoxTracker<F>) -> Self {
+        Self {
+            tracker_id: track.tracker_id,
+            class_id: track.class_id,
+            state: track.track_state(),
+            box_coords: track.get_state_bbox(),
+            time_since_update: track.time_since_update,
+            number_of_successful_updates: track.number_of_successful_updates,
         }
+    }
+}
 
-        self.split_detections(detections);
+/// Configuration of any tracker, tagged by algorithm so it can be loaded
+/// from a file and turned into a tracker with [`TrackerConfig::build`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "tracker", rename_all = "snake_case")]
+pub enum TrackerConfig {
+    #[serde(rename = "bytetrack")]
+    ByteTrack(ByteTrackConfig),
+    Sort(SortConfig),
+    #[serde(rename = "ocsort")]
+    OcSort(OcSortConfig),
+    #[serde(rename = "botsort")]
+    BotSort(BotSortConfig),
+}
 
-        self.high_conf_boxes.clear();
-        for d in &self.high_conf_detections {
-            self.high_conf_boxes.push(d.box_coords);
+impl TrackerConfig {
+    /// Validates the config of the selected algorithm.
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            Self::ByteTrack(config) => config.validate(),
+            Self::Sort(config) => config.validate(),
+            Self::OcSort(config) => config.validate(),
+            Self::BotSort(config) => config.validate(),
         }
+    }
 
-        self.low_conf_boxes.clear();
-        for d in &self.low_conf_detections {
-            self.low_conf_boxes.push(d.box_coords);
-        }
+    /// Builds the configured tracker at precision `F`, e.g.
+    /// `config.build::<f64>()`.
+    pub fn build<F: Float>(&self) -> Result<Box<dyn Tracker<F>>> {
+        Ok(match self {
+            Self::ByteTrack(config) => Box::new(ByteTrackTracker::new(config.clone())?),
+            Self::Sort(config) => Box::new(SortTracker::new(config.clone())?),
+            Self::OcSort(config) => Box::new(OcSortTracker::new(config.clone())?),
+            Self::BotSort(config) => Box::new(BotSortTracker::new(config.clone())?),
+        })
+    }
+}
 
-        self.predicted_boxes.clear();
-        for t in &self.tracks {
-            self.predicted_boxes.push(t.get_state_bbox());
-        }
+pub(crate) fn active_tracks<'a, F: Float>(
+    tracks: impl IntoIterator<Item = &'a KalmanBoxTracker<F>>,
+) -> Vec<TrackInfo<F>> {
+    tracks
+        .into_iter()
+        .filter(|t| t.time_since_update == 0)
+        .map(TrackInfo::from)
+        .collect()
+}
 
-        self.matched_indices.clear();
-        self.unmatched_track_indices.clear();
-        self.unmatched_det_indices.clear();
-
-        if !self.high_conf_boxes.is_empty() && !self.predicted_boxes.is_empty() {
-            let iou_matrix = compute_iou_batch(&self.predicted_boxes, &self.high_conf_boxes);
-            let (matched, unmatched_tracks, unmatched_dets) =
-                get_associated_indices(&iou_matrix, self.minimum_iou_threshold);
-            self.matched_indices = matched;
-            self.unmatched_track_indices = unmatched_tracks;
-            self.unmatched_det_indices = unmatched_dets;
-        } else {
-            self.unmatched_track_indices.extend(0..self.tracks.len());
-            self.unmatched_det_indices
-                .extend(0..self.high_conf_boxes.len());
-        }
+pub(crate) fn lost_tracks<'a, F: Float>(
+    tracks: impl IntoIterator<Item = &'a KalmanBoxTracker<F>>,
+) -> Vec<TrackInfo<F>> {
+    tracks
+        .into_iter()
+        .filter(|t| t.track_state() == TrackState::Lost)
+        .map(TrackInfo::from)
+        .collect()
+}
 
-        Self::update_detections(
-            &mut self.tracks,
-            &self.high_conf_detections,
-            &mut self.updated_detections,
-            &self.matched_indices,
-            self.minimum_consecutive_frames,
-            &mut self.next_tracker_id,
-        );
-
-        self.remaining_predicted_boxes.clear();
-        for &idx in &self.unmatched_track_indices {
-            self.remaining_predicted_boxes
-                .push(self.predicted_boxes[idx]);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::TrackEventKind;
+
+    fn configs() -> [(TrackerConfig, &'static str, &'static str); 4] {
+        [
+            (
+                TrackerConfig::ByteTrack(ByteTrackConfig::default().with_lost_track_buffer(45)),
+                "ByteTrack",
+                "bytetrack",
+            ),
+            (
+                TrackerConfig::Sort(SortConfig::default().with_lost_track_buffer(45)),
+                "SORT",
+                "sort",
+            ),
+            (
+                TrackerConfig::OcSort(OcSortConfig::default().with_lost_track_buffer(45)),
+                "OC-SORT",
+                "ocsort",
+            ),
+            (
+                TrackerConfig::BotSort(BotSortConfig::default().with_lost_track_buffer(45)),
+                "BoT-SORT",
+                "botsort",
+            ),
+        ]
+    }
+
+    #[test]
+    fn boxed_trackers_dispatch_to_their_algorithm() {
+        let detections = [Detection::new([100.0, 100.0, 150.0, 200.0], 0, 0.9)];
+        for (config, name, _) in configs() {
+            let mut tracker: Box<dyn Tracker> = config.build().unwrap();
+            let mut into_tracker = config.build::<f32>().unwrap();
+            assert_eq!(tracker.name(), name);
+            assert_eq!(tracker.config(), config);
+
+            let mut output = Vec::new();
+            for _ in 0..4 {
+                let tracked = tracker.update(&detections).unwrap();
+                into_tracker.update_into(&detections, &mut output).unwrap();
+                assert_eq!(tracked.len(), 1);
+                assert_eq!(tracked[0].tracker_id, output[0].tracker_id, "{name}");
+            }
+            assert_eq!(output[0].tracker_id, 0, "{name}");
+            assert_eq!(tracker.active_tracks().len(), 1, "{name}");
+
+            tracker.reset();
+            assert!(tracker.active_tracks().is_empty());
+            assert!(tracker.events().is_empty());
+            assert_eq!(tracker.update(&detections).unwrap()[0].tracker_id, -1);
+            let events = tracker.events();
+            assert_eq!(events.len(), 1, "{name}");
+            assert_eq!(
+                (events[0].kind, events[0].frame_index),
+                (TrackEventKind::Created, 0)
+            );
         }
+    }
 
-        self.matched_indices_adjusted.clear();
-        let unmatched_det_indices2 =
-            if !self.low_conf_boxes.is_empty() && !self.remaining_predicted_boxes.is_empty() {
-                let iou_matrix =
-                    compute_iou_batch(&self.remaining_predicted_boxes, &self.low_conf_boxes);
-                let (matched2, _, unmatched_dets2) =
-                    get_associated_indices(&iou_matrix, self.minimum_iou_threshold);
-                for &(i, j) in &matched2 {
-                    self.matched_indices_adjusted
-                        .push((self.unmatched_track_indices[i], j));
-                }
-                unmatched_dets2
-            } else {
-                (0..self.low_conf_boxes.len()).collect()
+    #[test]
+    fn tracker_config_round_trips_with_its_tag() {
+        for (config, name, tag) in configs() {
+            let json = serde_json::to_value(&config).unwrap();
+            assert_eq!(json["tracker"], tag, "{name}");
+            assert_eq!(json["lost_track_buffer"], 45, "{name}");
+            let parsed: TrackerConfig = serde_json::from_value(json).unwrap();
+            assert_eq!(parsed, config);
+
+            // Omitted fields take their defaults.
+            let defaults = match config {
+                TrackerConfig::ByteTrack(_) => TrackerConfig::ByteTrack(Default::default()),
+                TrackerConfig::Sort(_) => TrackerConfig::Sort(Default::default()),
+                TrackerConfig::OcSort(_) => TrackerConfig::OcSort(Default::default()),
+                TrackerConfig::BotSort(_) => TrackerConfig::BotSort(Default::default()),
             };
-
-        Self::update_detections(
-            &mut self.tracks,
-            &self.low_conf_detections,
-            &mut self.updated_detections,
-            &self.matched_indices_adjusted,
-            self.minimum_consecutive_frames,
-            &mut self.next_tracker_id,
-        );
-
-        for &det_idx in &unmatched_det_indices2 {
-            self.updated_detections.push(TrackedDetection {
-                box_coords: self.low_conf_boxes[det_idx],
-                tracker_id: -1,
-            });
+            let minimal = format!(r#"{{"tracker": "{tag}"}}"#);
+            assert_eq!(
+                serde_json::from_str::<TrackerConfig>(&minimal).unwrap(),
+                defaults
+            );
         }
 
-        let high_conf_dets = self.high_conf_detections.clone();
-        let high_conf_bxs = self.high_conf_boxes.clone();
-        let unmatched_dets = self.unmatched_det_indices.clone();
-        self.spawn_new_trackers(&high_conf_dets, &high_conf_bxs, &unmatched_dets);
-
-        self.alive_indices.clear();
-        let alive = get_alive_trackers(
-            &self.tracks,
-            self.minimum_consecutive_frames,
-            self.maximum_frames_without_update,
-        );
-        self.alive_indices.extend(alive);
-
-        let new_tracks: Vec<KalmanBoxTracker> = self
-            .alive_indices
-            .iter()
-            .map(|&i| self.tracks[i].clone())
-            .collect();
-        self.tracks = new_tracks;
-
-        std::mem::take(&mut self.updated_detections)
+        assert!(serde_json::from_str::<TrackerConfig>(r#"{"tracker": "byte_track"}"#).is_err());
     }
 }
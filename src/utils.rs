@@ -1,17 +1,26 @@
-use super::kalman::KalmanBoxTracker;
+use std::cmp::Ordering;
 
-pub fn get_alive_trackers(
-    trackers: &[KalmanBoxTracker],
+use super::kalman::KalmanBoxTracker;
+use crate::assignment::{connected_groups, AssignmentSolver};
+use crate::error::{Result, TrackerError};
+use crate::float::Float;
+use crate::iou::IouMetric;
+use crate::simd::{iou_rows_into, BoxColumns};
+use crate::spatial::SparseSimilarity;
+
+pub fn get_alive_trackers<F: Float>(
+    trackers: &[KalmanBoxTracker<F>],
     minimum_consecutive_frames: i32,
     maximum_frames_without_update: i32,
 ) -> Vec<usize> {
     let mut alive_indices = Vec::new();
 
     for (idx, tracker) in trackers.iter().enumerate() {
-        let is_mature = tracker.number_of_successful_updates >= minimum_consecutive_frames;
-        let is_active = tracker.time_since_update == 0;
-
-        if tracker.time_since_update < maximum_frames_without_update && (is_mature || is_active) {
+        if is_alive(
+            tracker,
+            minimum_consecutive_frames,
+            maximum_frames_without_update,
+        ) {
             alive_indices.push(idx);
         }
     }
@@ -19,186 +28,875 @@ pub fn get_alive_trackers(
     alive_indices
 }
 
-pub fn linear_sum_assignment(
-    cost_matrix: &[f32],
+pub(crate) fn is_alive<F: Float>(
+    tracker: &KalmanBoxTracker<F>,
+    minimum_consecutive_frames: i32,
+    maximum_frames_without_update: i32,
+) -> bool {
+    let is_mature = tracker.number_of_successful_updates >= minimum_consecutive_frames;
+    let is_active = tracker.time_since_update == 0;
+
+    tracker.time_since_update < maximum_frames_without_update && (is_mature || is_active)
+}
+
+/// Keeps the tracks [`get_alive_trackers`] would select, in order, and moves
+/// the others into `removed` marked as removed, also in order. Surviving
+/// confirmed tracks without a match in the latest frame become lost.
+///
+/// `kalman` gives the filter of a track, so that OC-SORT can prune its
+/// extended tracks too.
+pub(crate) fn prune_tracks<F: Float, T>(
+    tracks: &mut Vec<T>,
+    kalman: fn(&mut T) -> &mut KalmanBoxTracker<F>,
+    minimum_consecutive_frames: i32,
+    maximum_frames_without_update: i32,
+    removed: &mut Vec<KalmanBoxTracker<F>>,
+) {
+    removed.clear();
+    tracks.retain_mut(|track| {
+        let track = kalman(track);
+        if !is_alive(
+            track,
+            minimum_consecutive_frames,
+            maximum_frames_without_update,
+        ) {
+            let mut removed_track = track.clone();
+            removed_track.mark_removed();
+            removed.push(removed_track);
+            return false;
+        }
+        if track.time_since_update > 0 {
+            track.mark_lost();
+        }
+        true
+    });
+}
+
+/// Solves the rectangular linear sum assignment problem for a row-major
+/// `n_rows x n_cols` cost matrix, minimizing total cost like
+/// `scipy.optimize.linear_sum_assignment`.
+///
+/// Returns the assigned column for every row, `None` for unassigned rows.
+/// Fails like scipy's `ValueError` on NaN or `-inf` costs and on matrices
+/// where `+inf` entries leave no complete assignment. Use [`LsapWorkspace`]
+/// to also get the total cost and to reuse buffers between calls.
+pub fn linear_sum_assignment<F: Float>(
+    cost_matrix: &[F],
+    n_rows: usize,
+    n_cols: usize,
+) -> Result<Vec<Option<usize>>> {
+    let mut assignment = Vec::new();
+    LsapWorkspace::new().solve(cost_matrix, n_rows, n_cols, &mut assignment)?;
+    Ok(assignment)
+}
+
+/// Unchecked [`linear_sum_assignment`] for the trackers' own cost matrices,
+/// which are finite.
+pub(crate) fn solve_assignment<F: Float>(
+    cost_matrix: &[F],
     n_rows: usize,
     n_cols: usize,
 ) -> Vec<Option<usize>> {
-    if n_rows == 0 || n_cols == 0 {
-        return vec![None; n_rows];
+    let mut assignment = Vec::new();
+    LsapWorkspace::new().solve_unchecked(cost_matrix, n_rows, n_cols, &mut assignment);
+    assignment
+}
+
+/// Buffers of the shortest augmenting path solver behind
+/// [`linear_sum_assignment`]. Kept between calls, solving does not allocate
+/// once they have grown to the largest problem size.
+#[derive(Debug, Clone, Default)]
+pub struct LsapWorkspace<F: Float = f32> {
+    transposed_cost: Vec<F>,
+    u: Vec<F>,
+    v: Vec<F>,
+    shortest_path_costs: Vec<F>,
+    path: Vec<usize>,
+    col4row: Vec<usize>,
+    row4col: Vec<usize>,
+    sr: Vec<bool>,
+    sc: Vec<bool>,
+    remaining: Vec<usize>,
+}
+
+impl<F: Float> LsapWorkspace<F> {
+    pub fn new() -> Self {
+        Self::default()
     }
 
-    let mut nr = n_rows;
-    let mut nc = n_cols;
-    let mut transposed = false;
+    /// Same as [`linear_sum_assignment`], writing the assigned column of
+    /// every row to `assignment` and returning the total cost of the
+    /// assigned pairs. `assignment` is left all `None` on error.
+    pub fn solve(
+        &mut self,
+        cost_matrix: &[F],
+        n_rows: usize,
+        n_cols: usize,
+        assignment: &mut Vec<Option<usize>>,
+    ) -> Result<F> {
+        assignment.clear();
+        assignment.resize(n_rows, None);
+        if cost_matrix.len() != n_rows * n_cols {
+            return Err(TrackerError::CostMatrixShape {
+                len: cost_matrix.len(),
+                n_rows,
+                n_cols,
+            });
+        }
 
-    let mut cost_owned: Vec<f32>;
-    let cost: &[f32];
+        let neg_infinity = F::of_f64(f64::NEG_INFINITY);
+        // NaN compares to nothing, so it is rejected with `-inf`.
+        if let Some(index) = cost_matrix
+            .iter()
+            .position(|cost| cost.partial_cmp(&neg_infinity) != Some(Ordering::Greater))
+        {
+            return Err(TrackerError::InvalidCost {
+                row: index / n_cols,
+                col: index % n_cols,
+            });
+        }
 
-    if nc < nr {
-        cost_owned = vec![0.0f32; nr * nc];
-        for i in 0..nr {
-            for j in 0..nc {
-                cost_owned[j * nr + i] = cost_matrix[i * nc + j];
-            }
+        if !self.solve_unchecked(cost_matrix, n_rows, n_cols, assignment) {
+            assignment.fill(None);
+            return Err(TrackerError::InfeasibleCostMatrix);
         }
-        std::mem::swap(&mut nr, &mut nc);
-        transposed = true;
-        cost = &cost_owned;
-    } else {
-        cost = cost_matrix;
+
+        Ok(assignment
+            .iter()
+            .enumerate()
+            .filter_map(|(row, col)| col.map(|col| cost_matrix[row * n_cols + col]))
+            .fold(F::zero(), |total, cost| total + cost))
     }
 
-    let mut u = vec![0.0f32; nr];
-    let mut v = vec![0.0f32; nc];
-    let mut shortest_path_costs = vec![f32::INFINITY; nc];
-    let mut path = vec![usize::MAX; nc];
-    let mut col4row = vec![usize::MAX; nr];
-    let mut row4col = vec![usize::MAX; nc];
-    let mut sr = vec![false; nr];
-    let mut sc = vec![false; nc];
-    let mut remaining = vec![0usize; nc];
+    /// [`solve`](Self::solve) without validating the matrix. Returns `false`
+    /// when a row has no finite augmenting path, leaving it and the rows
+    /// after it unassigned.
+    pub(crate) fn solve_unchecked(
+        &mut self,
+        cost_matrix: &[F],
+        n_rows: usize,
+        n_cols: usize,
+        assignment: &mut Vec<Option<usize>>,
+    ) -> bool {
+        assignment.clear();
+        assignment.resize(n_rows, None);
+        if n_rows == 0 || n_cols == 0 {
+            return true;
+        }
 
-    for cur_row in 0..nr {
-        let mut min_val = 0.0f32;
+        let mut nr = n_rows;
+        let mut nc = n_cols;
+        let transposed = nc < nr;
 
-        let mut num_remaining = nc;
-        #[allow(clippy::needless_range_loop)]
-        for it in 0..nc {
-            remaining[it] = nc - it - 1;
+        if transposed {
+            self.transposed_cost.clear();
+            self.transposed_cost.resize(nr * nc, F::zero());
+            for i in 0..nr {
+                for j in 0..nc {
+                    self.transposed_cost[j * nr + i] = cost_matrix[i * nc + j];
+                }
+            }
+            std::mem::swap(&mut nr, &mut nc);
         }
+        let cost: &[F] = if transposed {
+            &self.transposed_cost
+        } else {
+            cost_matrix
+        };
+
+        let infinity = F::of_f64(f64::INFINITY);
+        let Self {
+            u,
+            v,
+            shortest_path_costs,
+            path,
+            col4row,
+            row4col,
+            sr,
+            sc,
+            remaining,
+            ..
+        } = self;
+        reset(u, nr, F::zero());
+        reset(v, nc, F::zero());
+        reset(shortest_path_costs, nc, infinity);
+        reset(path, nc, usize::MAX);
+        reset(col4row, nr, usize::MAX);
+        reset(row4col, nc, usize::MAX);
+        reset(sr, nr, false);
+        reset(sc, nc, false);
+        reset(remaining, nc, 0);
+
+        let mut feasible = true;
+        for cur_row in 0..nr {
+            let mut min_val = F::zero();
+
+            let mut num_remaining = nc;
+            #[allow(clippy::needless_range_loop)]
+            for it in 0..nc {
+                remaining[it] = nc - it - 1;
+            }
 
-        sr.fill(false);
-        sc.fill(false);
-        shortest_path_costs.fill(f32::INFINITY);
+            sr.fill(false);
+            sc.fill(false);
+            shortest_path_costs.fill(infinity);
+
+            let mut sink = usize::MAX;
+            let mut i = cur_row;
+
+            while sink == usize::MAX {
+                let mut index = usize::MAX;
+                let mut lowest = infinity;
+                sr[i] = true;
+
+                #[allow(clippy::needless_range_loop)]
+                for it in 0..num_remaining {
+                    let j = remaining[it];
+                    let r = min_val + cost[i * nc + j] - u[i] - v[j];
+
+                    if r < shortest_path_costs[j] {
+                        path[j] = i;
+                        shortest_path_costs[j] = r;
+                    }
+
+                    if shortest_path_costs[j] < lowest
+                        || (shortest_path_costs[j] == lowest && row4col[j] == usize::MAX)
+                    {
+                        lowest = shortest_path_costs[j];
+                        index = it;
+                    }
+                }
 
-        let mut sink = usize::MAX;
-        let mut i = cur_row;
+                min_val = lowest;
+                if min_val == infinity {
+                    break;
+                }
 
-        while sink == usize::MAX {
-            let mut index = usize::MAX;
-            let mut lowest = f32::INFINITY;
-            sr[i] = true;
+                let j = remaining[index];
+                if row4col[j] == usize::MAX {
+                    sink = j;
+                } else {
+                    i = row4col[j];
+                }
 
-            #[allow(clippy::needless_range_loop)]
-            for it in 0..num_remaining {
-                let j = remaining[it];
-                let r = min_val + cost[i * nc + j] - u[i] - v[j];
+                sc[j] = true;
+                remaining[index] = remaining[num_remaining - 1];
+                num_remaining -= 1;
+            }
 
-                if r < shortest_path_costs[j] {
-                    path[j] = i;
-                    shortest_path_costs[j] = r;
+            if sink == usize::MAX {
+                feasible = false;
+                break;
+            }
+
+            u[cur_row] += min_val;
+            for ii in 0..nr {
+                if sr[ii] && ii != cur_row && col4row[ii] != usize::MAX {
+                    u[ii] += min_val - shortest_path_costs[col4row[ii]];
                 }
+            }
 
-                if shortest_path_costs[j] < lowest
-                    || (shortest_path_costs[j] == lowest && row4col[j] == usize::MAX)
-                {
-                    lowest = shortest_path_costs[j];
-                    index = it;
+            for j in 0..nc {
+                if sc[j] {
+                    v[j] -= min_val - shortest_path_costs[j];
                 }
             }
 
-            min_val = lowest;
-            if min_val == f32::INFINITY {
-                break;
+            let mut j = sink;
+            loop {
+                let ii = path[j];
+                row4col[j] = ii;
+                j = std::mem::replace(&mut col4row[ii], j);
+                if ii == cur_row {
+                    break;
+                }
             }
+        }
 
-            let j = remaining[index];
-            if row4col[j] == usize::MAX {
-                sink = j;
-            } else {
-                i = row4col[j];
+        if transposed {
+            for col in 0..nc {
+                let row = row4col[col];
+                if row != usize::MAX {
+                    assignment[col] = Some(row);
+                }
             }
+        } else {
+            for i in 0..nr {
+                if col4row[i] != usize::MAX {
+                    assignment[i] = Some(col4row[i]);
+                }
+            }
+        }
+        feasible
+    }
+}
+
+/// Clears `buffer` and fills it with `len` copies of `value`.
+fn reset<T: Clone>(buffer: &mut Vec<T>, len: usize, value: T) {
+    buffer.clear();
+    buffer.resize(len, value);
+}
+
+pub fn get_associated_indices<F: Float>(
+    similarity_matrix: &nalgebra::DMatrix<F>,
+    min_similarity_thresh: F,
+) -> (Vec<(usize, usize)>, Vec<usize>, Vec<usize>) {
+    associated_indices(
+        AssignmentSolver::Exact,
+        similarity_matrix,
+        min_similarity_thresh,
+    )
+}
+
+/// [`get_associated_indices`] with the assignment solved by `solver`.
+pub(crate) fn associated_indices<F: Float>(
+    solver: AssignmentSolver,
+    similarity_matrix: &nalgebra::DMatrix<F>,
+    min_similarity_thresh: F,
+) -> (Vec<(usize, usize)>, Vec<usize>, Vec<usize>) {
+    let mut workspace = AssociationWorkspace::default();
+    workspace.set_similarity(similarity_matrix);
+    let mut matches = Matches::default();
+    workspace.associate(solver, min_similarity_thresh, &mut matches);
+    matches.into_parts()
+}
+
+/// Matched pairs and unmatched indices of an association, kept between
+/// frames to reuse the buffers.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Matches {
+    /// `(track, detection)` pairs sorted by track.
+    pub pairs: Vec<(usize, usize)>,
+    pub unmatched_tracks: Vec<usize>,
+    pub unmatched_detections: Vec<usize>,
+}
+
+impl Matches {
+    pub fn set(&mut self, parts: (Vec<(usize, usize)>, Vec<usize>, Vec<usize>)) {
+        (self.pairs, self.unmatched_tracks, self.unmatched_detections) = parts;
+    }
+
+    pub fn into_parts(self) -> (Vec<(usize, usize)>, Vec<usize>, Vec<usize>) {
+        (self.pairs, self.unmatched_tracks, self.unmatched_detections)
+    }
+
+    /// Every track and detection unmatched.
+    pub fn set_unmatched(&mut self, n_tracks: usize, n_detections: usize) {
+        self.pairs.clear();
+        self.unmatched_tracks.clear();
+        self.unmatched_tracks.extend(0..n_tracks);
+        self.unmatched_detections.clear();
+        self.unmatched_detections.extend(0..n_detections);
+    }
+}
+
+/// Buffers of a dense association, kept between frames so that associating
+/// with the exact solver does not allocate once they have grown.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct AssociationWorkspace<F: Float> {
+    /// Row-major similarity of tracks (rows) and detections (columns).
+    pub similarity: Vec<F>,
+    pub n_rows: usize,
+    pub n_cols: usize,
+    /// Row-major pairs never matched, whatever their similarity.
+    forbidden: Vec<bool>,
+    /// Detection boxes laid out for the IoU kernel.
+    detection_columns: BoxColumns<F>,
+    cost: Vec<F>,
+    assignment: Vec<Option<usize>>,
+    lsap: LsapWorkspace<F>,
+    detection_matched: Vec<bool>,
+}
+
+impl<F: Float> AssociationWorkspace<F> {
+    pub fn set_similarity(&mut self, similarity_matrix: &nalgebra::DMatrix<F>) {
+        self.n_rows = similarity_matrix.nrows();
+        self.n_cols = similarity_matrix.ncols();
+        self.similarity.clear();
+        for i in 0..self.n_rows {
+            self.similarity
+                .extend(similarity_matrix.row(i).iter().copied());
+        }
+        self.allow_all();
+    }
 
-            sc[j] = true;
-            remaining[index] = remaining[num_remaining - 1];
-            num_remaining -= 1;
+    /// Sets the similarity to the IoU of `predicted_boxes` and
+    /// `detection_boxes`.
+    pub fn set_iou(&mut self, predicted_boxes: &[[F; 4]], detection_boxes: &[[F; 4]]) {
+        self.n_rows = predicted_boxes.len();
+        self.n_cols = detection_boxes.len();
+        self.detection_columns.fill(detection_boxes);
+        iou_rows_into(
+            predicted_boxes,
+            &self.detection_columns,
+            &mut self.similarity,
+        );
+        self.allow_all();
+    }
+
+    /// Sets the similarity by `metric`, see [`IouMetric::similarity_batch`].
+    pub fn set_metric(
+        &mut self,
+        metric: IouMetric,
+        predicted_boxes: &[[F; 4]],
+        detection_boxes: &[[F; 4]],
+    ) {
+        if metric == IouMetric::Iou {
+            return self.set_iou(predicted_boxes, detection_boxes);
+        }
+        self.n_rows = predicted_boxes.len();
+        self.n_cols = detection_boxes.len();
+        self.similarity.clear();
+        for pred_box in predicted_boxes {
+            self.similarity.extend(
+                detection_boxes
+                    .iter()
+                    .map(|det_box| metric.similarity(pred_box, det_box)),
+            );
         }
+        self.allow_all();
+    }
+
+    fn allow_all(&mut self) {
+        self.forbidden.clear();
+        self.forbidden.resize(self.n_rows * self.n_cols, false);
+    }
+
+    /// Prevents the pair from being matched.
+    pub fn forbid(&mut self, row: usize, col: usize) {
+        self.forbidden[row * self.n_cols + col] = true;
+    }
 
-        if sink == usize::MAX {
-            break;
+    /// Multiplies the similarity of every detection by its score.
+    pub fn scale_columns(&mut self, scores: &[F]) {
+        for (k, value) in self.similarity.iter_mut().enumerate() {
+            *value *= scores[k % self.n_cols];
         }
+    }
 
-        u[cur_row] += min_val;
-        for ii in 0..nr {
-            if sr[ii] && ii != cur_row && col4row[ii] != usize::MAX {
-                u[ii] += min_val - shortest_path_costs[col4row[ii]];
+    /// Forbids the pairs of a track and a detection of different classes.
+    pub fn forbid_other_classes(&mut self, track_classes: &[i32], detection_classes: &[i32]) {
+        for (i, &track_class) in track_classes.iter().enumerate() {
+            for (j, &detection_class) in detection_classes.iter().enumerate() {
+                if track_class != detection_class {
+                    self.forbid(i, j);
+                }
             }
         }
+    }
 
-        for j in 0..nc {
-            if sc[j] {
-                v[j] -= min_val - shortest_path_costs[j];
+    /// Forbids the pairs flagged in the row-major `gate`.
+    pub fn apply_gate(&mut self, gate: Option<&[bool]>) {
+        if let Some(gate) = gate {
+            for (forbidden, &gated) in self.forbidden.iter_mut().zip(gate) {
+                *forbidden |= gated;
             }
         }
+    }
 
-        let mut j = sink;
-        loop {
-            let ii = path[j];
-            row4col[j] = ii;
-            j = std::mem::replace(&mut col4row[ii], j);
-            if ii == cur_row {
-                break;
+    /// Solves the assignment on the similarity like [`associated_indices`],
+    /// writing the result to `matches`.
+    ///
+    /// Forbidden pairs cost more than any other pair and than the solver's
+    /// cost limit, and are dropped from the result. A fixed gated similarity
+    /// could not do this for costs whose minimum similarity is below it.
+    pub fn associate(
+        &mut self,
+        solver: AssignmentSolver,
+        min_similarity_thresh: F,
+        matches: &mut Matches,
+    ) {
+        let (n_rows, n_cols) = (self.n_rows, self.n_cols);
+        if n_rows == 0 || n_cols == 0 {
+            matches.set_unmatched(n_rows, n_cols);
+            return;
+        }
+
+        let cost_limit = -min_similarity_thresh;
+        let forbidden_cost = self
+            .similarity
+            .iter()
+            .zip(&self.forbidden)
+            .filter(|&(_, &forbidden)| !forbidden)
+            .fold(cost_limit.max(F::zero()), |max, (&value, _)| {
+                max.max(-value)
+            })
+            + F::one();
+        self.cost.clear();
+        self.cost.extend(
+            self.similarity
+                .iter()
+                .zip(&self.forbidden)
+                .map(|(&value, &forbidden)| if forbidden { forbidden_cost } else { -value }),
+        );
+        solver.solve_into(
+            &self.cost,
+            n_rows,
+            n_cols,
+            cost_limit,
+            &mut self.lsap,
+            &mut self.assignment,
+        );
+
+        matches.pairs.clear();
+        matches.unmatched_tracks.clear();
+        self.detection_matched.clear();
+        self.detection_matched.resize(n_cols, false);
+        for (row, &opt_col) in self.assignment.iter().enumerate() {
+            match opt_col {
+                Some(col)
+                    if !self.forbidden[row * n_cols + col]
+                        && self.similarity[row * n_cols + col] >= min_similarity_thresh =>
+                {
+                    matches.pairs.push((row, col));
+                    self.detection_matched[col] = true;
+                }
+                _ => matches.unmatched_tracks.push(row),
             }
         }
+        matches.unmatched_detections.clear();
+        matches
+            .unmatched_detections
+            .extend((0..n_cols).filter(|&col| !self.detection_matched[col]));
+    }
+}
+
+/// [`get_associated_indices`] on a [`SparseSimilarity`], solving each group
+/// of tracks and detections connected by stored pairs on its own.
+///
+/// Returns the same matches as the dense solve for nonnegative similarities.
+/// The dense solver breaks ties by its visiting order, which a group solved
+/// apart does not reproduce, so a group with several best matchings sends the
+/// whole problem to the dense solver, as does a threshold of zero or less.
+pub fn get_sparse_associated_indices<F: Float>(
+    similarity: &SparseSimilarity<F>,
+    min_similarity_thresh: F,
+) -> (Vec<(usize, usize)>, Vec<usize>, Vec<usize>) {
+    sparse_associated_indices(AssignmentSolver::Exact, similarity, min_similarity_thresh)
+}
+
+/// [`get_sparse_associated_indices`] with each group solved by `solver`.
+pub(crate) fn sparse_associated_indices<F: Float>(
+    solver: AssignmentSolver,
+    similarity: &SparseSimilarity<F>,
+    min_similarity_thresh: F,
+) -> (Vec<(usize, usize)>, Vec<usize>, Vec<usize>) {
+    // Unstored pairs reach a threshold of zero, so no group stays apart.
+    if min_similarity_thresh <= F::zero() {
+        return associated_indices(solver, &similarity.to_dense(), min_similarity_thresh);
+    }
+
+    let (n_rows, n_cols) = (similarity.nrows(), similarity.ncols());
+    let entries = similarity.entries();
+    let mut row_start = vec![0; n_rows + 1];
+    for &(row, _, _) in entries {
+        row_start[row + 1] += 1;
+    }
+    for row in 0..n_rows {
+        row_start[row + 1] += row_start[row];
     }
 
-    let mut assignment;
-    if transposed {
-        let mut row_assignments: Vec<Option<usize>> = vec![None; n_rows];
-        for col in 0..nc {
-            let row = row4col[col];
-            if row != usize::MAX {
-                row_assignments[col] = Some(row);
+    let mut matched_indices = Vec::new();
+    let mut local_col = vec![usize::MAX; n_cols];
+    let groups = connected_groups(n_rows, n_cols, entries.iter().map(|&(r, c, _)| (r, c)));
+    for (rows, cols) in &groups {
+        if rows.is_empty() || cols.is_empty() {
+            continue;
+        }
+        if rows.len() == 1 && cols.len() == 1 {
+            if similarity.get(rows[0], cols[0]) >= min_similarity_thresh {
+                matched_indices.push((rows[0], cols[0]));
             }
+            continue;
+        }
+
+        for (j, &col) in cols.iter().enumerate() {
+            local_col[col] = j;
         }
-        assignment = row_assignments;
-    } else {
-        assignment = vec![None; n_rows];
-        for i in 0..nr {
-            if col4row[i] != usize::MAX {
-                assignment[i] = Some(col4row[i]);
+        let mut group_similarity = nalgebra::DMatrix::zeros(rows.len(), cols.len());
+        for (i, &row) in rows.iter().enumerate() {
+            for &(_, col, value) in &entries[row_start[row]..row_start[row + 1]] {
+                group_similarity[(i, local_col[col])] = value;
             }
         }
+        let matched = if solver == AssignmentSolver::Exact {
+            let Some(assignment) = unique_best_assignment(&group_similarity) else {
+                return associated_indices(solver, &similarity.to_dense(), min_similarity_thresh);
+            };
+            assignment
+                .into_iter()
+                .enumerate()
+                .filter_map(|(i, col)| col.map(|j| (i, j)))
+                .filter(|&(i, j)| group_similarity[(i, j)] >= min_similarity_thresh)
+                .collect()
+        } else {
+            associated_indices(solver, &group_similarity, min_similarity_thresh).0
+        };
+        matched_indices.extend(matched.into_iter().map(|(i, j)| (rows[i], cols[j])));
     }
 
-    assignment
+    matched_indices.sort_unstable();
+    unmatched_complement(matched_indices, n_rows, n_cols)
+}
+
+/// Assignment of the best matching of the nonnegative `similarity`, or
+/// `None` if another matching reaches the same total up to rounding.
+///
+/// Any other best matching lacks one of the positive pairs of the one found,
+/// so it wins once each of those pairs costs a little more, while a unique
+/// one keeps winning as long as the penalties stay within its margin.
+fn unique_best_assignment<F: Float>(
+    similarity: &nalgebra::DMatrix<F>,
+) -> Option<Vec<Option<usize>>> {
+    let (n_rows, n_cols) = similarity.shape();
+    let mut cost: Vec<F> = (0..n_rows)
+        .flat_map(|i| (0..n_cols).map(move |j| -similarity[(i, j)]))
+        .collect();
+    let positive_pairs = |cost: &[F], assignment: &[Option<usize>]| -> Vec<usize> {
+        assignment
+            .iter()
+            .enumerate()
+            .filter_map(|(i, col)| col.map(|j| i * n_cols + j))
+            .filter(|&k| cost[k] < F::zero())
+            .collect()
+    };
+
+    let assignment = solve_assignment(&cost, n_rows, n_cols);
+    let best = positive_pairs(&cost, &assignment);
+    let largest = cost.iter().fold(F::zero(), |largest, &c| largest.max(-c));
+    let penalty = F::default_epsilon() * F::of_f64(16.0) * (largest + F::one());
+    for &k in &best {
+        cost[k] += penalty;
+    }
+    let penalized = solve_assignment(&cost, n_rows, n_cols);
+    (positive_pairs(&cost, &penalized) == best).then_some(assignment)
 }
 
-pub fn get_associated_indices(
-    similarity_matrix: &nalgebra::DMatrix<f32>,
-    min_similarity_thresh: f32,
+/// How an association stage compares boxes and solves the assignment.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct BoxAssociation {
+    pub metric: IouMetric,
+    pub solver: AssignmentSolver,
+    /// Compare only boxes found to overlap through a spatial index, where
+    /// `metric` allows it.
+    pub spatial_index: bool,
+}
+
+impl BoxAssociation {
+    pub fn associate<F: Float>(
+        &self,
+        predicted_boxes: &[[F; 4]],
+        detection_boxes: &[[F; 4]],
+        min_similarity_thresh: F,
+    ) -> (Vec<(usize, usize)>, Vec<usize>, Vec<usize>) {
+        if self.spatial_index {
+            if let Some(similarity) = self
+                .metric
+                .similarity_sparse(predicted_boxes, detection_boxes)
+            {
+                return sparse_associated_indices(self.solver, &similarity, min_similarity_thresh);
+            }
+        }
+        let similarity = self
+            .metric
+            .similarity_batch(predicted_boxes, detection_boxes);
+        associated_indices(self.solver, &similarity, min_similarity_thresh)
+    }
+}
+
+/// Associates tracks with detections of the same class only. Pairs of
+/// different classes are forbidden in the IoU matrix, which is then solved
+/// like [`get_associated_indices`].
+pub fn get_class_associated_indices<F: Float>(
+    predicted_boxes: &[[F; 4]],
+    track_classes: &[i32],
+    detection_boxes: &[[F; 4]],
+    detection_classes: &[i32],
+    min_similarity_thresh: F,
 ) -> (Vec<(usize, usize)>, Vec<usize>, Vec<usize>) {
-    let n_trackers = similarity_matrix.nrows();
-    let n_detections = similarity_matrix.ncols();
+    let mut workspace = AssociationWorkspace::default();
+    workspace.set_iou(predicted_boxes, detection_boxes);
+    workspace.forbid_other_classes(track_classes, detection_classes);
+    let mut matches = Matches::default();
+    workspace.associate(AssignmentSolver::Exact, min_similarity_thresh, &mut matches);
+    matches.into_parts()
+}
 
-    let mut matched_indices = Vec::new();
-    let mut unmatched_tracks: Vec<usize> = (0..n_trackers).collect();
-    let mut unmatched_detections: Vec<usize> = (0..n_detections).collect();
+/// Completes sorted `matched_indices` with the unmatched tracks and
+/// detections in ascending order.
+fn unmatched_complement(
+    matched_indices: Vec<(usize, usize)>,
+    n_tracks: usize,
+    n_detections: usize,
+) -> (Vec<(usize, usize)>, Vec<usize>, Vec<usize>) {
+    let mut track_matched = vec![false; n_tracks];
+    let mut det_matched = vec![false; n_detections];
+    for &(row, col) in &matched_indices {
+        track_matched[row] = true;
+        det_matched[col] = true;
+    }
+
+    let unmatched_tracks = (0..n_tracks).filter(|&i| !track_matched[i]).collect();
+    let unmatched_detections = (0..n_detections).filter(|&j| !det_matched[j]).collect();
 
-    if n_trackers > 0 && n_detections > 0 {
-        let cost_matrix = -similarity_matrix;
+    (matched_indices, unmatched_tracks, unmatched_detections)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iou::compute_iou_batch;
+    use crate::spatial::compute_iou_sparse;
+
+    fn next(state: &mut u64) -> f64 {
+        *state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (*state >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// `n` boxes on a coarse grid of positions and sizes, so that many boxes
+    /// coincide or tie in IoU.
+    fn crowd(state: &mut u64, n: usize, side: f64) -> Vec<[f64; 4]> {
+        (0..n)
+            .map(|_| {
+                let x = (next(state) * side / 10.0).floor() * 10.0;
+                let y = (next(state) * side / 10.0).floor() * 10.0;
+                let w = 20.0 + (next(state) * 3.0).floor() * 10.0;
+                let h = 40.0 + (next(state) * 3.0).floor() * 20.0;
+                [x, y, x + w, y + h]
+            })
+            .collect()
+    }
 
-        let mut flat_cost = vec![0.0f32; n_trackers * n_detections];
-        for i in 0..n_trackers {
-            for j in 0..n_detections {
-                flat_cost[i * n_detections + j] = cost_matrix[(i, j)];
+    /// Random scenes on a coarse grid, where many boxes coincide and IoUs
+    /// tie, associated at thresholds including zero.
+    #[test]
+    fn sparse_association_matches_dense() {
+        let mut state = 1u64;
+        let mut tied_scenes = 0;
+        for round in 0..400 {
+            let n = 1 + round % 30;
+            let side = 40.0 + next(&mut state) * 300.0;
+            let tracks = crowd(&mut state, n, side);
+            let detections = crowd(&mut state, n + round % 5, side);
+            let dense_similarity = compute_iou_batch(&tracks, &detections);
+            let sparse_similarity = compute_iou_sparse(&tracks, &detections);
+            for thresh in [0.0, 0.1, 0.3] {
+                let dense = get_associated_indices(&dense_similarity, thresh);
+                assert_eq!(
+                    get_sparse_associated_indices(&sparse_similarity, thresh),
+                    dense,
+                    "round {round}, threshold {thresh}"
+                );
+            }
+            if unique_best_assignment(&dense_similarity).is_none() {
+                tied_scenes += 1;
             }
         }
+        assert!(tied_scenes > 50, "{tied_scenes}");
+    }
+
+    #[test]
+    fn row_of_infinite_costs_is_infeasible() {
+        let inf = f64::INFINITY;
+        let mut workspace = LsapWorkspace::<f64>::new();
+        let mut assignment = Vec::new();
+        for (cost, n_rows, n_cols) in [
+            (vec![1.0, 2.0, inf, inf], 2, 2),
+            (vec![inf, inf, inf, 1.0, 2.0, 3.0], 2, 3),
+            (vec![1.0, inf, 2.0, inf, 3.0, inf], 3, 2),
+        ] {
+            assert_eq!(
+                workspace.solve(&cost, n_rows, n_cols, &mut assignment),
+                Err(TrackerError::InfeasibleCostMatrix)
+            );
+            assert_eq!(assignment, vec![None; n_rows]);
+        }
+
+        // `+inf` entries are fine while a complete assignment avoids them.
+        let total = workspace
+            .solve(&[inf, 1.0, 2.0, inf], 2, 2, &mut assignment)
+            .unwrap();
+        assert_eq!(total, 3.0);
+        assert_eq!(assignment, [Some(1), Some(0)]);
+    }
+
+    #[test]
+    fn nan_and_negative_infinite_costs_are_rejected() {
+        let mut workspace = LsapWorkspace::<f64>::new();
+        let mut assignment = Vec::new();
+        for bad in [f64::NAN, f64::NEG_INFINITY] {
+            let cost = [1.0, 2.0, 3.0, 4.0, bad, 6.0];
+            assert_eq!(
+                workspace.solve(&cost, 2, 3, &mut assignment),
+                Err(TrackerError::InvalidCost { row: 1, col: 1 })
+            );
+            assert_eq!(assignment, [None, None]);
+        }
+    }
 
-        let assignment = linear_sum_assignment(&flat_cost, n_trackers, n_detections);
+    #[test]
+    fn solve_returns_the_minimum_total_cost() {
+        let mut workspace = LsapWorkspace::<f64>::new();
+        let mut assignment = Vec::new();
 
-        for (row, opt_col) in assignment.iter().enumerate() {
-            if let Some(col) = opt_col {
-                let iou = similarity_matrix[(row, *col)];
-                if iou >= min_similarity_thresh {
-                    matched_indices.push((row, *col));
-                    unmatched_tracks.retain(|&x| x != row);
-                    unmatched_detections.retain(|&x| x != *col);
+        // Rows 0 and 1 both prefer column 1, the optimum gives it to row 0.
+        let square = [4.0, 1.0, 3.0, 2.0, 0.0, 5.0, 3.0, 2.0, 2.0];
+        assert_eq!(workspace.solve(&square, 3, 3, &mut assignment), Ok(5.0));
+        assert_eq!(assignment, [Some(1), Some(0), Some(2)]);
+
+        let wide = [5.0, 1.0, 3.0, 2.0, 4.0, 6.0];
+        assert_eq!(workspace.solve(&wide, 2, 3, &mut assignment), Ok(3.0));
+        assert_eq!(assignment, [Some(1), Some(0)]);
+
+        let tall = [5.0, 2.0, 1.0, 4.0, 3.0, 6.0];
+        assert_eq!(workspace.solve(&tall, 3, 2, &mut assignment), Ok(3.0));
+        assert_eq!(assignment, [Some(1), Some(0), None]);
+    }
+
+    #[test]
+    fn reused_workspace_matches_a_fresh_one() {
+        let mut state = 3u64;
+        let mut reused = LsapWorkspace::<f64>::new();
+        let mut assignment = Vec::new();
+        let mut expected = Vec::new();
+        for (n_rows, n_cols) in [(40, 50), (3, 3), (60, 20), (2, 5), (1, 1), (0, 4)] {
+            let cost: Vec<f64> = (0..n_rows * n_cols).map(|_| next(&mut state)).collect();
+            let total = reused.solve(&cost, n_rows, n_cols, &mut assignment);
+            let expected_total = LsapWorkspace::new().solve(&cost, n_rows, n_cols, &mut expected);
+            assert_eq!(total, expected_total, "{n_rows}x{n_cols}");
+            assert_eq!(assignment, expected, "{n_rows}x{n_cols}");
+        }
+    }
+
+    #[test]
+    fn class_association_never_pairs_other_classes() {
+        let mut state = 11u64;
+        for round in 0..100 {
+            let n = 1 + round % 12;
+            let tracks = crowd(&mut state, n, 100.0);
+            let detections = crowd(&mut state, n + round % 3, 100.0);
+            let classes = |state: &mut u64, len| -> Vec<i32> {
+                (0..len).map(|_| (next(state) * 3.0) as i32).collect()
+            };
+            let track_classes = classes(&mut state, tracks.len());
+            let detection_classes = classes(&mut state, detections.len());
+            for thresh in [0.0, 0.3] {
+                let (matched, unmatched_tracks, unmatched_detections) =
+                    get_class_associated_indices(
+                        &tracks,
+                        &track_classes,
+                        &detections,
+                        &detection_classes,
+                        thresh,
+                    );
+                for &(row, col) in &matched {
+                    assert_eq!(track_classes[row], detection_classes[col], "round {round}");
                 }
+                assert_eq!(matched.len() + unmatched_tracks.len(), tracks.len());
+                assert_eq!(matched.len() + unmatched_detections.len(), detections.len());
             }
         }
     }
-
-    (matched_indices, unmatched_tracks, unmatched_detections)
 }
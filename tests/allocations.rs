@@ -0,0 +1,127 @@
+//! Checks that `update_into` stops allocating once the tracker's buffers
+//! have grown to the scene size.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use trackers_rs::{
+    BotSortConfig, BotSortTracker, ByteTrackConfig, ByteTrackTracker, Detection, Tracker,
+};
+
+/// System allocator counting the allocations of each thread, so that tests
+/// running in parallel do not see each other's.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn count_allocation() {
+    // Fails only while the thread is being torn down.
+    let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count_allocation();
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count_allocation();
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// `frames` frames of `n` people of about 40x100 px walking at constant
+/// velocity, with jittered boxes, a mix of high and low scores and a few
+/// misses.
+fn crowd_sequence(n: usize, frames: usize) -> Vec<Vec<Detection>> {
+    let mut state = 7u64;
+    let mut next = move || {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (state >> 40) as f32 / (1u64 << 24) as f32
+    };
+
+    let side = (n as f32).sqrt() * 60.0;
+    let people: Vec<([f32; 4], [f32; 2])> = (0..n)
+        .map(|_| {
+            let (x, y) = (next() * side, next() * side);
+            let (w, h) = (30.0 + next() * 20.0, 80.0 + next() * 40.0);
+            let velocity = [(next() - 0.5) * 6.0, (next() - 0.5) * 2.0];
+            ([x, y, x + w, y + h], velocity)
+        })
+        .collect();
+
+    (0..frames)
+        .map(|t| {
+            people
+                .iter()
+                .filter_map(|(b, v)| {
+                    if next() < 0.05 {
+                        return None;
+                    }
+                    let dx = v[0] * t as f32 + (next() - 0.5) * 2.0;
+                    let dy = v[1] * t as f32 + (next() - 0.5) * 2.0;
+                    let score = if next() > 0.2 { 0.9 } else { 0.3 };
+                    Some(Detection::new(
+                        [b[0] + dx, b[1] + dy, b[2] + dx, b[3] + dy],
+                        0,
+                        score,
+                    ))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Runs the crowd once to grow every buffer, then replays it after a reset
+/// and asserts that the replay did not allocate.
+fn assert_no_steady_state_allocations(tracker: &mut dyn Tracker) {
+    let sequence = crowd_sequence(100, 30);
+    let mut output = Vec::new();
+    for detections in &sequence {
+        tracker.update_into(detections, &mut output).unwrap();
+    }
+    tracker.reset();
+
+    let before = ALLOCATIONS.with(Cell::get);
+    for detections in &sequence {
+        tracker.update_into(detections, &mut output).unwrap();
+    }
+    let allocations = ALLOCATIONS.with(Cell::get) - before;
+    assert_eq!(
+        allocations,
+        0,
+        "{} update_into allocated {allocations} times",
+        tracker.name()
+    );
+}
+
+#[test]
+fn bytetrack_update_into_does_not_allocate() {
+    let mut tracker = ByteTrackTracker::new(ByteTrackConfig::default()).unwrap();
+    assert_no_steady_state_allocations(&mut tracker);
+}
+
+#[test]
+fn botsort_update_into_does_not_allocate() {
+    let mut tracker = BotSortTracker::new(BotSortConfig::default()).unwrap();
+    assert_no_steady_state_allocations(&mut tracker);
+}
+
+#[test]
+fn class_aware_bytetrack_update_into_does_not_allocate() {
+    let config = ByteTrackConfig::default().with_class_aware(true);
+    let mut tracker = ByteTrackTracker::new(config).unwrap();
+    assert_no_steady_state_allocations(&mut tracker);
+}
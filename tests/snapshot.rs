@@ -0,0 +1,123 @@
+//! Restoring ByteTrack from JSON snapshots continues exactly like the
+//! uninterrupted tracker.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use trackers_rs::{
+    ByteTrackConfig, ByteTrackSnapshot, ByteTrackTracker, Detection, Float, MotionModel,
+    TrackEventKind, Tracker, SNAPSHOT_VERSION,
+};
+
+/// `frames` frames of people entering and leaving the scene at different
+/// times, with jittered boxes, high and low scores and a few misses, so that
+/// tracks are created, lost, refound and removed.
+fn synthetic_sequence<F: Float>(frames: usize) -> Vec<Vec<Detection<F>>> {
+    let mut state = 42u64;
+    let mut next = move || {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (state >> 11) as f64 / (1u64 << 53) as f64
+    };
+
+    let people: Vec<([f64; 4], [f64; 2], usize, usize)> = (0..20)
+        .map(|_| {
+            let (x, y) = (next() * 800.0, next() * 600.0);
+            let (w, h) = (30.0 + next() * 20.0, 80.0 + next() * 40.0);
+            let velocity = [(next() - 0.5) * 8.0, (next() - 0.5) * 4.0];
+            let enter = (next() * frames as f64 * 0.6) as usize;
+            let leave = enter + 20 + (next() * frames as f64 * 0.4) as usize;
+            ([x, y, x + w, y + h], velocity, enter, leave)
+        })
+        .collect();
+
+    (0..frames)
+        .map(|t| {
+            people
+                .iter()
+                .filter(|&&(_, _, enter, leave)| (enter..leave).contains(&t))
+                .filter_map(|(b, v, _, _)| {
+                    if next() < 0.1 {
+                        return None;
+                    }
+                    let dx = v[0] * t as f64 + (next() - 0.5) * 3.0;
+                    let dy = v[1] * t as f64 + (next() - 0.5) * 3.0;
+                    let score = if next() > 0.25 { 0.9 } else { 0.4 };
+                    Some(Detection::new(
+                        [b[0] + dx, b[1] + dy, b[2] + dx, b[3] + dy].map(F::of_f64),
+                        0,
+                        F::of_f64(score),
+                    ))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Runs the sequence uninterrupted and, side by side, restored from a JSON
+/// snapshot every few frames, and asserts identical output and events.
+fn assert_restores_match<F: Float + Serialize + DeserializeOwned>(config: ByteTrackConfig) {
+    let mut uninterrupted = ByteTrackTracker::<F>::new(config.clone()).unwrap();
+    let mut restored = ByteTrackTracker::<F>::new(config).unwrap();
+    let mut removed = 0;
+
+    for (frame, detections) in synthetic_sequence::<F>(150).iter().enumerate() {
+        if frame % 7 == 3 {
+            let json = serde_json::to_string(&restored.snapshot()).unwrap();
+            let snapshot: ByteTrackSnapshot<F> = serde_json::from_str(&json).unwrap();
+            assert_eq!(snapshot.frame_count(), frame as u64);
+            restored = ByteTrackTracker::restore(snapshot).unwrap();
+        }
+
+        let expected = uninterrupted.update(detections).unwrap();
+        let actual = restored.update(detections).unwrap();
+        assert_eq!(
+            serde_json::to_string(&expected).unwrap(),
+            serde_json::to_string(&actual).unwrap(),
+            "frame {frame}"
+        );
+        assert_eq!(uninterrupted.events(), restored.events(), "frame {frame}");
+        removed += restored
+            .events()
+            .iter()
+            .filter(|event| event.kind == TrackEventKind::Removed)
+            .count();
+    }
+
+    assert!(removed > 0);
+    assert_eq!(
+        serde_json::to_string(&uninterrupted.snapshot()).unwrap(),
+        serde_json::to_string(&restored.snapshot()).unwrap()
+    );
+}
+
+#[test]
+fn restored_f32_tracker_matches_uninterrupted_run() {
+    assert_restores_match::<f32>(ByteTrackConfig::default());
+}
+
+#[test]
+fn restored_f64_tracker_matches_uninterrupted_run() {
+    assert_restores_match::<f64>(ByteTrackConfig::default());
+    assert_restores_match::<f64>(ByteTrackConfig::default().with_motion_model(MotionModel::Xyah));
+}
+
+#[test]
+fn snapshot_of_another_version_is_rejected() {
+    let mut tracker = ByteTrackTracker::<f32>::new(ByteTrackConfig::default()).unwrap();
+    for detections in synthetic_sequence(10) {
+        tracker.update(&detections).unwrap();
+    }
+    let mut json = serde_json::to_value(tracker.snapshot()).unwrap();
+    assert_eq!(json["version"], SNAPSHOT_VERSION);
+    assert!(serde_json::from_value::<ByteTrackSnapshot>(json.clone()).is_ok());
+
+    for version in [0, SNAPSHOT_VERSION + 1] {
+        json["version"] = version.into();
+        let error = serde_json::from_value::<ByteTrackSnapshot>(json.clone()).unwrap_err();
+        assert!(
+            error.to_string().contains("unsupported snapshot version"),
+            "{error}"
+        );
+    }
+}